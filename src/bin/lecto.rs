@@ -0,0 +1,472 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use lecto_client::client::BulkItem;
+use lecto_client::remind_group::remind::Remind;
+use lecto_client::tz::{self, JST};
+use lecto_client::{
+    BulkOptions, BulkReport, Client, Debt, DebtRequest, DebtStatus, DebtStatusRequest,
+    DebtStatusVariable, Debtor, DebtorRequest, KycDone, RetryPolicy,
+};
+
+/// Lecto APIをシェルから操作する
+#[derive(Debug, Parser)]
+#[command(name = "lecto", version)]
+struct Cli {
+    #[arg(long, env = "LECTO_API_KEY", hide_env_values = true)]
+    api_key: String,
+
+    #[arg(long, env = "LECTO_BASE_URL")]
+    base_url: String,
+
+    /// リクエストのタイムアウト (秒)
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+
+    /// リトライを含めた最大試行回数
+    #[arg(long, default_value_t = 3)]
+    max_attempts: usize,
+
+    #[arg(long, value_enum, default_value_t = Output::Json)]
+    output: Output,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 債務者
+    Debtor {
+        #[command(subcommand)]
+        command: CreateCommand,
+    },
+    /// 債権
+    Debt {
+        #[command(subcommand)]
+        command: CreateCommand,
+    },
+    /// 債権ステータス
+    DebtStatus {
+        #[command(subcommand)]
+        command: DebtStatusCommand,
+    },
+    /// リマインド
+    Reminds {
+        #[command(subcommand)]
+        command: RemindsCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CreateCommand {
+    /// ファイルか標準入力のリクエストを登録する
+    Create(InputArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum DebtStatusCommand {
+    /// 債権のステータスを変更する
+    Set(DebtStatusArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum RemindsCommand {
+    /// リマインド対象を一覧する
+    List {
+        #[arg(long)]
+        group: u64,
+        /// YYYY-MM-DD
+        #[arg(long)]
+        date: NaiveDate,
+    },
+}
+
+#[derive(Debug, Args)]
+struct InputArgs {
+    /// 入力ファイル。`-` または省略で標準入力
+    #[arg(long, short)]
+    file: Option<PathBuf>,
+
+    /// 省略時はファイルの拡張子から判断し、標準入力ならjson
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    #[arg(long, default_value_t = 4)]
+    concurrency: usize,
+}
+
+#[derive(Debug, Args)]
+struct DebtStatusArgs {
+    /// 指定した場合は他の引数を使わずファイルのリクエストを送る
+    #[arg(long, short, conflicts_with_all = ["debt_id", "status"])]
+    file: Option<PathBuf>,
+
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    #[arg(long, required_unless_present = "file")]
+    debt_id: Option<String>,
+
+    /// active, repaid, suspended など
+    #[arg(long, required_unless_present = "file")]
+    status: Option<String>,
+
+    #[arg(long)]
+    status_id: Option<String>,
+
+    /// RFC3339。省略時は現在時刻
+    #[arg(long)]
+    changed_at: Option<DateTime<FixedOffset>>,
+
+    /// RFC3339。省略時は 9999-12-31T23:59:59
+    #[arg(long)]
+    expire_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    Json,
+    Table,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<ExitCode> {
+    let cli = Cli::parse();
+    let client = Client::builder()
+        .api_key(cli.api_key)
+        .base_url(cli.base_url)
+        .timeout(Duration::from_secs(cli.timeout))
+        .retry_policy(RetryPolicy::default().with_max_attempts(cli.max_attempts))
+        .build()?;
+
+    match cli.command {
+        Command::Debtor {
+            command: CreateCommand::Create(input),
+        } => {
+            let reqs: Vec<DebtorRequest> = read_records(input.file.as_deref(), input.format)?;
+            let options = BulkOptions::default().with_concurrency(input.concurrency);
+            let report = client.bulk_upsert_debtors(reqs, &options).await;
+            print_report(&report, cli.output)
+        }
+        Command::Debt {
+            command: CreateCommand::Create(input),
+        } => {
+            let reqs: Vec<DebtRequest> = read_records(input.file.as_deref(), input.format)?;
+            let options = BulkOptions::default().with_concurrency(input.concurrency);
+            let report = client.bulk_upsert_debts(reqs, &options).await;
+            print_report(&report, cli.output)
+        }
+        Command::DebtStatus {
+            command: DebtStatusCommand::Set(args),
+        } => {
+            // ステータスの順番が変わらないよう1件ずつ送り、失敗しても残りを続ける
+            let mut items = vec![];
+            for (index, req) in debt_status_requests(args)?.into_iter().enumerate() {
+                let id = req.debt_id.clone();
+                let result = client.patch_debt_statuses(req).await;
+                items.push(BulkItem { index, id, result });
+            }
+            print_report(&BulkReport { items }, cli.output)
+        }
+        Command::Reminds {
+            command: RemindsCommand::List { group, date },
+        } => {
+            let reminds = client.get_reminds(group, date).await?;
+            print_rows(&reminds, cli.output)?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn debt_status_requests(args: DebtStatusArgs) -> anyhow::Result<Vec<DebtStatusRequest>> {
+    if let Some(file) = args.file {
+        return read_records(Some(&file), args.format);
+    }
+
+    let status = args.status.context("--status is required")?;
+    let status: DebtStatusVariable =
+        serde_json::from_value(serde_json::Value::String(status)).context("unknown --status")?;
+    if let DebtStatusVariable::Unknown(status) = &status {
+        anyhow::bail!("unknown --status: {}", status);
+    }
+    Ok(vec![DebtStatusRequest {
+        debt_id: args.debt_id.context("--debt-id is required")?,
+        status_id: args.status_id,
+        status: Some(status),
+        changed_at: args.changed_at.unwrap_or_else(|| tz::now(&JST)),
+        expire_at: args
+            .expire_at
+            .unwrap_or_else(lecto_client::debt_status::default_expire_at),
+    }])
+}
+
+/// JSONはオブジェクト1件か配列、CSVはヘッダ付きで1行1件
+fn read_records<T: DeserializeOwned>(
+    file: Option<&Path>,
+    format: Option<Format>,
+) -> anyhow::Result<Vec<T>> {
+    let file = file.filter(|f| f.as_os_str() != "-");
+    let format = format
+        .or_else(|| match file?.extension()?.to_str()? {
+            "csv" => Some(Format::Csv),
+            _ => None,
+        })
+        .unwrap_or(Format::Json);
+
+    let mut input = String::new();
+    match file {
+        Some(path) => File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?
+            .read_to_string(&mut input)?,
+        None => io::stdin().read_to_string(&mut input)?,
+    };
+
+    parse_records(&input, format)
+}
+
+fn parse_records<T: DeserializeOwned>(input: &str, format: Format) -> anyhow::Result<Vec<T>> {
+    match format {
+        Format::Json => {
+            let value: serde_json::Value = serde_json::from_str(input)?;
+            Ok(match value {
+                serde_json::Value::Array(_) => serde_json::from_value(value)?,
+                _ => vec![serde_json::from_value(value)?],
+            })
+        }
+        Format::Csv => csv::Reader::from_reader(input.as_bytes())
+            .deserialize()
+            .enumerate()
+            .map(|(i, row)| row.with_context(|| format!("invalid csv row {}", i + 1)))
+            .collect(),
+    }
+}
+
+fn print_report<T: Serialize + TableRow>(
+    report: &BulkReport<T>,
+    output: Output,
+) -> anyhow::Result<ExitCode> {
+    let succeeded: Vec<&T> = report.succeeded().map(|(_, v)| v).collect();
+    print_rows(&succeeded, output)?;
+
+    for (item, e) in report.failed() {
+        eprintln!("#{} {}: {}", item.index, item.id, e);
+    }
+
+    Ok(if report.is_all_success() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn print_rows<T: Serialize + TableRow>(rows: &[T], output: Output) -> anyhow::Result<()> {
+    match output {
+        Output::Json => println!("{}", serde_json::to_string_pretty(rows)?),
+        Output::Table => print!("{}", render_table(rows)),
+    }
+    Ok(())
+}
+
+trait TableRow {
+    fn headers() -> Vec<&'static str>;
+    fn cells(&self) -> Vec<String>;
+}
+
+impl<T: TableRow> TableRow for &T {
+    fn headers() -> Vec<&'static str> {
+        T::headers()
+    }
+
+    fn cells(&self) -> Vec<String> {
+        (*self).cells()
+    }
+}
+
+impl TableRow for Debtor {
+    fn headers() -> Vec<&'static str> {
+        vec!["id", "debtor_id", "name", "email", "kyc_done"]
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.debtor_id.clone(),
+            self.basic_information.name.clone(),
+            self.email.email.clone(),
+            match &self.address.kyc_done {
+                KycDone::Done => "true".into(),
+                KycDone::NotDone => "false".into(),
+                KycDone::Unknown(v) => v.to_string(),
+            },
+        ]
+    }
+}
+
+impl TableRow for Debt {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "id",
+            "debt_id",
+            "debtor_id",
+            "debt_amount",
+            "repayment_due_at",
+            "status",
+        ]
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.debt_id.clone(),
+            self.debtor_id.clone(),
+            self.debt_amount.to_string(),
+            self.repayment_due_at.to_rfc3339(),
+            status_name(&self.debt_status.status),
+        ]
+    }
+}
+
+impl TableRow for DebtStatus {
+    fn headers() -> Vec<&'static str> {
+        vec!["id", "debt_id", "status", "changed_at", "expire_at"]
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.debt_id.clone(),
+            status_name(&self.status),
+            self.changed_at.to_rfc3339(),
+            self.expire_at.to_rfc3339(),
+        ]
+    }
+}
+
+impl TableRow for Remind {
+    fn headers() -> Vec<&'static str> {
+        vec!["label", "debtor_id", "name", "debts"]
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.label.clone(),
+            self.debtor.debtor_id.clone(),
+            self.debtor.basic_information.name.clone(),
+            self.debts
+                .iter()
+                .map(|d| d.debt_id.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+        ]
+    }
+}
+
+fn status_name(status: &DebtStatusVariable) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn render_table<T: TableRow>(rows: &[T]) -> String {
+    let headers = T::headers();
+    let cells: Vec<Vec<String>> = rows.iter().map(TableRow::cells).collect();
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([h.chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let line = |row: Vec<String>| {
+        row.iter()
+            .zip(&widths)
+            .map(|(cell, w)| format!("{}{}", cell, " ".repeat(w - cell.chars().count())))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+            + "\n"
+    };
+
+    std::iter::once(headers.iter().map(|h| h.to_string()).collect())
+        .chain(cells)
+        .map(line)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lecto_client::Gender;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_debtor_csv() -> anyhow::Result<()> {
+        let csv = "debtor_id,name,name_kana,birth_date,gender,email,address,kyc_done,postal_code,phone_number,mobile_number\n\
+                   d-1,名前,カナ,1999-01-01,male,sample@example.com,東京都,true,3336666,0312345678,09012345678\n\
+                   d-2,名前2,カナ,,none,sample2@example.com,東京都,false,3336666,,09012345678\n";
+
+        let reqs: Vec<DebtorRequest> = parse_records(csv, Format::Csv)?;
+
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[0].birth_date, NaiveDate::from_ymd_opt(1999, 1, 1));
+        assert_eq!(reqs[0].gender, Gender::Male);
+        assert!(reqs[0].kyc_done);
+        assert_eq!(reqs[1].birth_date, None);
+        assert_eq!(reqs[1].phone_number, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_object_or_array() -> anyhow::Result<()> {
+        let one = r#"{"debt_id": "1234-5678", "status": "repaid", "changed_at": "2021-11-15T12:34:00+09:00", "expire_at": "9999-12-31T23:59:59+09:00"}"#;
+        let reqs: Vec<DebtStatusRequest> = parse_records(one, Format::Json)?;
+        assert_eq!(reqs.len(), 1);
+
+        let many = format!("[{}, {}]", one, one);
+        let reqs: Vec<DebtStatusRequest> = parse_records(&many, Format::Json)?;
+        assert_eq!(reqs.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_table() {
+        struct Row(&'static str, &'static str);
+        impl TableRow for Row {
+            fn headers() -> Vec<&'static str> {
+                vec!["id", "name"]
+            }
+            fn cells(&self) -> Vec<String> {
+                vec![self.0.into(), self.1.into()]
+            }
+        }
+
+        assert_eq!(
+            render_table(&[Row("1", "a"), Row("100", "bb")]),
+            "id   name\n1    a\n100  bb\n"
+        );
+    }
+}
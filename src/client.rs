@@ -1,153 +1,775 @@
+use std::collections::HashSet;
 use std::fmt::Debug;
-use std::time::Duration;
+use std::future::Future;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use chrono::NaiveDate;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use futures::{stream, Stream, TryStreamExt};
 use reqwest::header::HeaderMap;
-use reqwest::{Response, StatusCode};
+use reqwest::{Response, Url};
 use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
 
-use crate::debtor::{DebtorRawRequest, DebtorResponse};
-use crate::remind_group::remind::{Remind, RemindResponse};
-use crate::util::join_url;
-use crate::{Debt, DebtRequest, DebtStatus, DebtStatusRequest, Debtor, DebtorRequest};
-
-/// TODO: debtorでもdebtsでも使えるようにrequest,responseをStringにしているがDebtor以外の型ができたタイミングでGenericsにしたほうがいい気がする
-#[derive(thiserror::Error, Debug)]
-pub enum LectoError {
-    #[error("Status: {status} Res: {response:#?}")]
-    UnprocessableEntity {
-        status: StatusCode,
-        request: String,
-        response: String,
-    },
-    #[error("Status: {status} Res: {response:#?}")]
-    BadRequest {
-        status: StatusCode,
-        request: String,
-        response: String,
-    },
-    #[error("Status: {status} Res: {response:#?}")]
-    InternalServerError {
-        status: StatusCode,
-        request: String,
-        response: String,
-    },
-}
+use crate::debtor::{
+    DebtorFilter, DebtorPatchRequest, DebtorRawPatchRequest, DebtorRawRequest, DebtorResponse,
+};
+use crate::error::Result;
+pub use crate::error::{ErrorResponse, LectoError};
+use crate::idempotency::{IdempotencyKey, IDEMPOTENCY_KEY_HEADER};
+use crate::pagination::{collect_all, Page, PageRequest};
+use crate::remind_group::query::{merge_by_label, RemindQuery};
+use crate::remind_group::remind::{
+    decode_remind, LenientReminds, Remind, RemindDecodeError, RemindResponse,
+};
+use crate::remind_group::{
+    RemindGroup, RemindGroupPatchRequest, RemindGroupRequest, RemindGroupStatus,
+};
+use crate::retry::RetryPolicy;
+use crate::strict::UnknownValues;
+use crate::tz;
+use crate::util::{join_url, parse_retry_after};
+use crate::{
+    Debt, DebtFilter, DebtPatchRequest, DebtRequest, DebtStatus, DebtStatusRequest,
+    DebtStatusVariable, Debtor, DebtorRequest, StatusTimeline,
+};
+
+mod builder;
+mod bulk;
+
+pub use builder::ClientBuilder;
+pub use bulk::{BulkEndpoint, BulkItem, BulkOptions, BulkReport};
 
 #[derive(Debug, Clone)]
 pub struct Client {
     api_key: String,
     base_url: String,
     client: reqwest::Client,
-    max_retry: usize,
+    default_headers: HeaderMap,
+    retry_policy: RetryPolicy,
+    log_level: log::Level,
+    page_size: usize,
+    lookup_before_retry: bool,
+    validate_before_send: bool,
+    strict: bool,
+    business_time_zone: FixedOffset,
+    ignore_remind_group_status: bool,
 }
 
 impl Client {
-    pub fn new(api_key: String, base_url: String, max_retry: usize, timeout_secs: u64) -> Client {
-        Client {
-            api_key,
-            base_url,
-            client: reqwest::Client::builder()
-                .timeout(Duration::from_secs(timeout_secs))
-                .build()
-                .unwrap(),
-            max_retry,
-        }
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
     }
 
-    pub async fn post_debtor(&self, req: DebtorRequest) -> anyhow::Result<Debtor> {
-        let headers = self.common_headers()?;
+    /// 業務タイムゾーン。`remind_at` や支払期日の日付はこのタイムゾーンで数える
+    pub fn business_time_zone(&self) -> FixedOffset {
+        self.business_time_zone
+    }
+
+    /// 業務タイムゾーンでの今日
+    pub fn today(&self) -> NaiveDate {
+        tz::today(&self.business_time_zone)
+    }
+
+    pub async fn post_debtor(&self, req: DebtorRequest) -> Result<Debtor> {
+        self.post_debtor_with_idempotency_key(req, IdempotencyKey::generate())
+            .await
+    }
+
+    /// `post_debtor` と同じだが、`Idempotency-Key` を呼び出し側で指定する
+    pub async fn post_debtor_with_idempotency_key(
+        &self,
+        req: DebtorRequest,
+        key: IdempotencyKey,
+    ) -> Result<Debtor> {
+        if self.validate_before_send {
+            req.validate_at(self.today())?;
+        }
         let url = join_url(&self.base_url, &["debtors"])?;
+        let lookup = join_url(&self.base_url, &["debtors", &req.debtor_id])?;
 
         let raw_req = DebtorRawRequest::from(req.clone());
-        let res = retry(
-            || {
+        let res = self
+            .execute_mutation(&key, Some(lookup), |headers| {
+                self.client
+                    .post(url.clone())
+                    .json(&raw_req)
+                    .headers(headers)
+                    .send()
+            })
+            .await?;
+
+        Self::handle_response(Some(req), res)
+            .await
+            .and_then(|v: DebtorResponse| self.check_unknown(v))
+            .map(Debtor::from)
+            .map_err(|e| e.with_idempotency_key(&key))
+    }
+
+    pub async fn get_debtor(&self, debtor_id: &str) -> Result<Debtor> {
+        let headers = self.common_headers()?;
+        let url = join_url(&self.base_url, &["debtors", debtor_id])?;
+
+        let res = self
+            .execute(|| {
                 let url = url.clone();
                 let headers = headers.clone();
-                self.client.post(url).json(&raw_req).headers(headers).send()
-            },
-            self.max_retry,
+                self.client.get(url).headers(headers).send()
+            })
+            .await?;
+
+        Self::handle_response(Some(debtor_id), res)
+            .await
+            .and_then(|v: DebtorResponse| self.check_unknown(v))
+            .map(Debtor::from)
+    }
+
+    pub async fn list_debtors(&self, filter: &DebtorFilter) -> Result<Vec<Debtor>> {
+        collect_all(self.debtors_stream(filter.clone())).await
+    }
+
+    /// `GET /debtors` をページを辿りながら1件ずつ返す
+    pub fn debtors_stream(&self, filter: DebtorFilter) -> impl Stream<Item = Result<Debtor>> + '_ {
+        self.paginate(
+            &["debtors"],
+            filter,
+            self.page_size,
+            true,
+            Debtor::from as fn(DebtorResponse) -> Debtor,
         )
-        .await?;
+    }
+
+    pub async fn patch_debtor(&self, debtor_id: &str, req: DebtorPatchRequest) -> Result<Debtor> {
+        self.patch_debtor_with_idempotency_key(debtor_id, req, IdempotencyKey::generate())
+            .await
+    }
+
+    /// `patch_debtor` と同じだが、`Idempotency-Key` を呼び出し側で指定する
+    pub async fn patch_debtor_with_idempotency_key(
+        &self,
+        debtor_id: &str,
+        req: DebtorPatchRequest,
+        key: IdempotencyKey,
+    ) -> Result<Debtor> {
+        let url = join_url(&self.base_url, &["debtors", debtor_id])?;
+
+        let raw_req = DebtorRawPatchRequest::from(req.clone());
+        let res = self
+            .execute_mutation(&key, None, |headers| {
+                self.client
+                    .patch(url.clone())
+                    .json(&raw_req)
+                    .headers(headers)
+                    .send()
+            })
+            .await?;
 
         Self::handle_response(Some(req), res)
             .await
-            .map(|v: DebtorResponse| Debtor::from(v))
+            .and_then(|v: DebtorResponse| self.check_unknown(v))
+            .map(Debtor::from)
+            .map_err(|e| e.with_idempotency_key(&key))
     }
 
-    pub async fn post_debt(&self, req: DebtRequest) -> anyhow::Result<Debt> {
+    pub async fn delete_debtor(&self, debtor_id: &str) -> Result<()> {
         let headers = self.common_headers()?;
-        let url = join_url(&self.base_url, &["debts"])?;
+        let url = join_url(&self.base_url, &["debtors", debtor_id])?;
 
-        let res = retry(
-            || {
+        let res = self
+            .execute(|| {
                 let url = url.clone();
                 let headers = headers.clone();
-                self.client.post(url).json(&req).headers(headers).send()
-            },
-            self.max_retry,
-        )
-        .await?;
+                self.client.delete(url).headers(headers).send()
+            })
+            .await?;
+
+        Self::handle_empty_response(Some(debtor_id), res).await
+    }
+
+    pub async fn post_debt(&self, req: DebtRequest) -> Result<Debt> {
+        self.post_debt_with_idempotency_key(req, IdempotencyKey::generate())
+            .await
+    }
+
+    /// `post_debt` と同じだが、`Idempotency-Key` を呼び出し側で指定する
+    pub async fn post_debt_with_idempotency_key(
+        &self,
+        req: DebtRequest,
+        key: IdempotencyKey,
+    ) -> Result<Debt> {
+        if self.validate_before_send {
+            req.validate()?;
+        }
+        let url = join_url(&self.base_url, &["debts"])?;
+        let lookup = join_url(&self.base_url, &["debts", &req.debt_id])?;
+
+        let res = self
+            .execute_mutation(&key, Some(lookup), |headers| {
+                self.client
+                    .post(url.clone())
+                    .json(&req)
+                    .headers(headers)
+                    .send()
+            })
+            .await?;
 
-        Self::handle_response(Some(req), res).await
+        Self::handle_response(Some(req), res)
+            .await
+            .and_then(|v: Debt| self.check_unknown(v))
+            .map_err(|e| e.with_idempotency_key(&key))
     }
 
-    pub async fn patch_debt_statuses(&self, req: DebtStatusRequest) -> anyhow::Result<DebtStatus> {
+    pub async fn get_debt(&self, debt_id: &str) -> Result<Debt> {
         let headers = self.common_headers()?;
-        let url = join_url(&self.base_url, &["debt_statuses"])?;
+        let url = join_url(&self.base_url, &["debts", debt_id])?;
 
-        let res = retry(
-            || {
+        let res = self
+            .execute(|| {
                 let url = url.clone();
                 let headers = headers.clone();
-                self.client.patch(url).json(&req).headers(headers).send()
-            },
-            self.max_retry,
+                self.client.get(url).headers(headers).send()
+            })
+            .await?;
+
+        Self::handle_response(Some(debt_id), res)
+            .await
+            .and_then(|v: Debt| self.check_unknown(v))
+    }
+
+    pub async fn list_debts(&self, filter: &DebtFilter) -> Result<Vec<Debt>> {
+        collect_all(self.debts_stream(filter.clone())).await
+    }
+
+    /// `GET /debts` をページを辿りながら1件ずつ返す
+    pub fn debts_stream(&self, filter: DebtFilter) -> impl Stream<Item = Result<Debt>> + '_ {
+        self.paginate(&["debts"], filter, self.page_size, true, |v: Debt| v)
+    }
+
+    pub async fn patch_debt(&self, debt_id: &str, req: DebtPatchRequest) -> Result<Debt> {
+        self.patch_debt_with_idempotency_key(debt_id, req, IdempotencyKey::generate())
+            .await
+    }
+
+    /// `patch_debt` と同じだが、`Idempotency-Key` を呼び出し側で指定する
+    pub async fn patch_debt_with_idempotency_key(
+        &self,
+        debt_id: &str,
+        req: DebtPatchRequest,
+        key: IdempotencyKey,
+    ) -> Result<Debt> {
+        let url = join_url(&self.base_url, &["debts", debt_id])?;
+
+        let res = self
+            .execute_mutation(&key, None, |headers| {
+                self.client
+                    .patch(url.clone())
+                    .json(&req)
+                    .headers(headers)
+                    .send()
+            })
+            .await?;
+
+        Self::handle_response(Some(req), res)
+            .await
+            .and_then(|v: Debt| self.check_unknown(v))
+            .map_err(|e| e.with_idempotency_key(&key))
+    }
+
+    pub async fn patch_debt_statuses(&self, req: DebtStatusRequest) -> Result<DebtStatus> {
+        self.patch_debt_statuses_with_idempotency_key(req, IdempotencyKey::generate())
+            .await
+    }
+
+    /// `patch_debt_statuses` と同じだが、`Idempotency-Key` を呼び出し側で指定する
+    pub async fn patch_debt_statuses_with_idempotency_key(
+        &self,
+        req: DebtStatusRequest,
+        key: IdempotencyKey,
+    ) -> Result<DebtStatus> {
+        let url = join_url(&self.base_url, &["debt_statuses"])?;
+
+        let res = self
+            .execute_mutation(&key, None, |headers| {
+                self.client
+                    .patch(url.clone())
+                    .json(&req)
+                    .headers(headers)
+                    .send()
+            })
+            .await?;
+
+        Self::handle_response(Some(req), res)
+            .await
+            .and_then(|v: DebtStatus| self.check_unknown(v))
+            .map_err(|e| e.with_idempotency_key(&key))
+    }
+
+    /// `GET /debt_statuses?debt_id=` で債権のステータス履歴をすべて取得する
+    pub async fn list_debt_statuses(&self, debt_id: &str) -> Result<Vec<DebtStatus>> {
+        collect_all(self.debt_statuses_stream(debt_id)).await
+    }
+
+    /// `GET /debt_statuses?debt_id=` をページを辿りながら1件ずつ返す
+    pub fn debt_statuses_stream(
+        &self,
+        debt_id: &str,
+    ) -> impl Stream<Item = Result<DebtStatus>> + '_ {
+        self.paginate(
+            &["debt_statuses"],
+            [("debt_id", debt_id.to_string())],
+            self.page_size,
+            true,
+            |v: DebtStatus| v,
         )
-        .await?;
+    }
+
+    /// ステータス履歴を取得して `StatusTimeline` にする
+    pub async fn debt_status_timeline(&self, debt_id: &str) -> Result<StatusTimeline> {
+        Ok(StatusTimeline::new(self.list_debt_statuses(debt_id).await?))
+    }
 
-        Self::handle_response(Some(req), res).await
+    /// 現在のステータスを `get_debt` で取得し、`to` へ遷移できる場合だけ
+    /// `at` から期限なしのステータスを登録する。`at` は業務タイムゾーンに直して送る
+    pub async fn transition_debt_status(
+        &self,
+        debt_id: &str,
+        to: DebtStatusVariable,
+        at: impl Into<DateTime<FixedOffset>>,
+    ) -> Result<DebtStatus> {
+        let at = at.into().with_timezone(&self.business_time_zone);
+        let current = self.get_debt(debt_id).await?.debt_status;
+        if !current.can_transition_to(&to) {
+            return Err(LectoError::IllegalTransition {
+                debt_id: debt_id.to_string(),
+                from: current.status,
+                to,
+            });
+        }
+        self.patch_debt_statuses(DebtStatusRequest::new(debt_id, to, at))
+            .await
     }
 
     pub async fn get_reminds(
         &self,
         remind_group_id: u64,
         remind_at: NaiveDate,
-    ) -> anyhow::Result<Vec<Remind>> {
+    ) -> Result<Vec<Remind>> {
+        self.query_reminds(remind_group_id, &RemindQuery::on(remind_at))
+            .await
+    }
+
+    /// `query` の期間を1日ずつ取得し、`label` ごとにまとめてから絞り込む
+    pub async fn query_reminds(
+        &self,
+        remind_group_id: u64,
+        query: &RemindQuery,
+    ) -> Result<Vec<Remind>> {
+        query.validate()?;
+        let ignore_status = query
+            .ignore_remind_group_status
+            .unwrap_or(self.ignore_remind_group_status);
+        let page_size = query.page_size.unwrap_or(self.page_size);
+
+        let mut reminds = vec![];
+        for remind_at in query.dates() {
+            let stream = self.paginate(
+                &Self::reminds_paths(remind_group_id),
+                Self::reminds_query(remind_at, ignore_status),
+                page_size,
+                false,
+                Remind::from as fn(RemindResponse) -> Remind,
+            );
+            reminds.extend(collect_all(stream).await?);
+        }
+        Ok(merge_by_label(reminds)
+            .into_iter()
+            .filter_map(|remind| query.filter(remind))
+            .collect())
+    }
+
+    /// `get_reminds` と同じだが1件ずつデコードし、デコードできなかったものは飛ばして
+    /// `errors` に入れる。通信やページ単位のエラーは `Err` のまま返す
+    pub async fn get_reminds_lenient(
+        &self,
+        remind_group_id: u64,
+        remind_at: NaiveDate,
+    ) -> Result<LenientReminds> {
+        let items = collect_all(self.paginate(
+            &Self::reminds_paths(remind_group_id),
+            Self::reminds_query(remind_at, self.ignore_remind_group_status),
+            self.page_size,
+            false,
+            |v: Value| v,
+        ))
+        .await?;
+
+        let mut result = LenientReminds::default();
+        for (index, raw) in items.into_iter().enumerate() {
+            let remind = decode_remind(index, raw.clone()).and_then(|v| {
+                self.check_unknown(v).map_err(|e| match e {
+                    LectoError::UnknownValue { field, value } => RemindDecodeError::new(
+                        index,
+                        &field,
+                        raw,
+                        format!("unknown value: {}", value),
+                    ),
+                    e => RemindDecodeError::new(index, "", raw, e.to_string()),
+                })
+            });
+            match remind {
+                Ok(remind) => result.reminds.push(Remind::from(remind)),
+                Err(e) => {
+                    log::warn!("❌ Failed to decode remind. {}: {}", e.path, e.message);
+                    result.errors.push(e);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// `GET /remind_groups/{id}/reminds` をページを辿りながら1件ずつ返す
+    pub fn reminds_stream(
+        &self,
+        remind_group_id: u64,
+        remind_at: NaiveDate,
+    ) -> impl Stream<Item = Result<Remind>> + '_ {
+        self.paginate(
+            &Self::reminds_paths(remind_group_id),
+            Self::reminds_query(remind_at, self.ignore_remind_group_status),
+            self.page_size,
+            false,
+            Remind::from as fn(RemindResponse) -> Remind,
+        )
+    }
+
+    fn reminds_paths(remind_group_id: u64) -> [String; 3] {
+        [
+            "remind_groups".into(),
+            remind_group_id.to_string(),
+            "reminds".into(),
+        ]
+    }
+
+    fn reminds_query(remind_at: NaiveDate, ignore_status: bool) -> [(&'static str, String); 2] {
+        [
+            ("remind_at", remind_at.to_string()),
+            ("ignore_remind_group_status", ignore_status.to_string()),
+        ]
+    }
+
+    pub async fn list_remind_groups(&self) -> Result<Vec<RemindGroup>> {
+        collect_all(self.remind_groups_stream()).await
+    }
+
+    /// `GET /remind_groups` をページを辿りながら1件ずつ返す
+    pub fn remind_groups_stream(&self) -> impl Stream<Item = Result<RemindGroup>> + '_ {
+        self.paginate(
+            &["remind_groups"],
+            Vec::<(String, String)>::new(),
+            self.page_size,
+            false,
+            |v: RemindGroup| v,
+        )
+    }
+
+    pub async fn get_remind_group(&self, remind_group_id: u64) -> Result<RemindGroup> {
         let headers = self.common_headers()?;
         let url = join_url(
             &self.base_url,
-            &[
-                "remind_groups",
-                remind_group_id.to_string().as_str(),
-                "reminds",
-            ],
+            &["remind_groups", &remind_group_id.to_string()],
         )?;
 
-        let res = retry(
-            || {
+        let res = self
+            .execute(|| {
                 let url = url.clone();
                 let headers = headers.clone();
+                self.client.get(url).headers(headers).send()
+            })
+            .await?;
+
+        Self::handle_response(Some(remind_group_id), res)
+            .await
+            .and_then(|v: RemindGroup| self.check_unknown(v))
+    }
+
+    pub async fn create_remind_group(&self, req: RemindGroupRequest) -> Result<RemindGroup> {
+        self.create_remind_group_with_idempotency_key(req, IdempotencyKey::generate())
+            .await
+    }
+
+    /// `create_remind_group` と同じだが、`Idempotency-Key` を呼び出し側で指定する
+    pub async fn create_remind_group_with_idempotency_key(
+        &self,
+        req: RemindGroupRequest,
+        key: IdempotencyKey,
+    ) -> Result<RemindGroup> {
+        let url = join_url(&self.base_url, &["remind_groups"])?;
+
+        let res = self
+            .execute_mutation(&key, None, |headers| {
                 self.client
-                    .get(url)
+                    .post(url.clone())
+                    .json(&req)
                     .headers(headers)
-                    .query(&[
-                        ("remind_at", remind_at.to_string().as_str()),
-                        ("ignore_remind_group_status", "true"),
-                    ])
                     .send()
+            })
+            .await?;
+
+        Self::handle_response(Some(req), res)
+            .await
+            .and_then(|v: RemindGroup| self.check_unknown(v))
+            .map_err(|e| e.with_idempotency_key(&key))
+    }
+
+    pub async fn update_remind_group(
+        &self,
+        remind_group_id: u64,
+        req: RemindGroupPatchRequest,
+    ) -> Result<RemindGroup> {
+        self.update_remind_group_with_idempotency_key(
+            remind_group_id,
+            req,
+            IdempotencyKey::generate(),
+        )
+        .await
+    }
+
+    /// `update_remind_group` と同じだが、`Idempotency-Key` を呼び出し側で指定する
+    pub async fn update_remind_group_with_idempotency_key(
+        &self,
+        remind_group_id: u64,
+        req: RemindGroupPatchRequest,
+        key: IdempotencyKey,
+    ) -> Result<RemindGroup> {
+        let url = join_url(
+            &self.base_url,
+            &["remind_groups", &remind_group_id.to_string()],
+        )?;
+
+        let res = self
+            .execute_mutation(&key, None, |headers| {
+                self.client
+                    .patch(url.clone())
+                    .json(&req)
+                    .headers(headers)
+                    .send()
+            })
+            .await?;
+
+        Self::handle_response(Some(req), res)
+            .await
+            .and_then(|v: RemindGroup| self.check_unknown(v))
+            .map_err(|e| e.with_idempotency_key(&key))
+    }
+
+    /// リマインドの送信を再開する
+    pub async fn activate_remind_group(&self, remind_group_id: u64) -> Result<RemindGroup> {
+        self.update_remind_group(
+            remind_group_id,
+            RemindGroupPatchRequest::status(RemindGroupStatus::Active),
+        )
+        .await
+    }
+
+    /// リマインドの送信を止める
+    pub async fn pause_remind_group(&self, remind_group_id: u64) -> Result<RemindGroup> {
+        self.update_remind_group(
+            remind_group_id,
+            RemindGroupPatchRequest::status(RemindGroupStatus::Paused),
+        )
+        .await
+    }
+
+    /// `assume_page_numbers` は、bodyが配列だけのときにも `page` で次ページを取りに行くか。
+    /// `page` を無視するエンドポイントでは `false` にする
+    fn paginate<'a, Q, V, T>(
+        &'a self,
+        paths: &[impl AsRef<str>],
+        query: Q,
+        page_size: usize,
+        assume_page_numbers: bool,
+        convert: fn(V) -> T,
+    ) -> impl Stream<Item = Result<T>> + 'a
+    where
+        Q: Serialize + Debug + Clone + 'a,
+        V: DeserializeOwned + UnknownValues + 'a,
+        T: 'a,
+    {
+        let paths: Vec<String> = paths.iter().map(|p| p.as_ref().to_string()).collect();
+        stream::try_unfold(
+            PaginationState::default(),
+            move |mut state: PaginationState| {
+                let paths = paths.clone();
+                let query = query.clone();
+                async move {
+                    let Some(page) = state.next.take() else {
+                        return Ok(None);
+                    };
+                    let (Page { items, next }, fingerprint) = self
+                        .fetch_page::<_, V>(&paths, &query, &page, page_size, assume_page_numbers)
+                        .await?;
+                    // 前のページと全く同じなら、サーバーがページ指定を無視している
+                    if state.previous == Some(fingerprint) {
+                        log::warn!("👻 Lecto returned the same page again for {:?}", page);
+                        return Ok(None);
+                    }
+                    // それより前のページに戻ったなら、カーソルやリンクが循環している
+                    if !state.seen.insert(fingerprint) {
+                        return Err(LectoError::Pagination(format!(
+                            "{:?} returned a page seen before",
+                            page
+                        )));
+                    }
+                    state.requested.insert(page);
+                    if let Some(next) = next.as_ref().filter(|n| state.requested.contains(n)) {
+                        return Err(LectoError::Pagination(format!(
+                            "next page {:?} was already requested",
+                            next
+                        )));
+                    }
+                    let items = items
+                        .into_iter()
+                        .map(|v| self.check_unknown(v).map(convert))
+                        .collect::<Result<Vec<_>>>()?;
+                    state.next = next;
+                    state.previous = Some(fingerprint);
+                    Result::Ok(Some((items, state)))
+                }
             },
-            self.max_retry,
         )
-        .await?;
+        .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    async fn fetch_page<Q: Serialize + Debug, V: DeserializeOwned>(
+        &self,
+        paths: &[String],
+        query: &Q,
+        page: &PageRequest,
+        page_size: usize,
+        assume_page_numbers: bool,
+    ) -> Result<(Page<V>, u64)> {
+        let headers = self.common_headers()?;
+        let url = join_url(&self.base_url, paths)?;
+        let per_page = page_size.to_string();
+
+        let res = self
+            .execute(|| {
+                let headers = headers.clone();
+                let req = match page {
+                    PageRequest::Url(next) => self.client.get(next.clone()),
+                    PageRequest::Number(n) => self
+                        .client
+                        .get(url.clone())
+                        .query(query)
+                        .query(&[("page", n.to_string().as_str()), ("per_page", &per_page)]),
+                    PageRequest::Cursor(cursor) => self
+                        .client
+                        .get(url.clone())
+                        .query(query)
+                        .query(&[("cursor", cursor.as_str()), ("per_page", &per_page)]),
+                };
+                req.headers(headers).send()
+            })
+            .await?;
+
+        let res = Self::error_for_status(Some(query), res).await?;
+        let headers = res.headers().clone();
+        let res_url = res.url().clone();
+        let body = res.text().await?;
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        let fingerprint = hasher.finish();
+        let page = Page::parse(
+            body,
+            &headers,
+            &res_url,
+            page,
+            page_size,
+            assume_page_numbers,
+        )?;
+        // APIキーを付けて送るので、よそのホストを指す `Link` は辿らない
+        if let Some(PageRequest::Url(next)) = &page.next {
+            if next.origin() != url.origin() {
+                return Err(LectoError::Pagination(format!(
+                    "next page {} is not on {}",
+                    next, self.base_url
+                )));
+            }
+        }
+        Ok((page, fingerprint))
+    }
 
-        Self::handle_response(None as Option<()>, res)
+    async fn execute<F, R>(&self, send: F) -> Result<Response>
+    where
+        R: Future<Output = reqwest::Result<Response>>,
+        F: Fn() -> R,
+    {
+        self.retry_policy
+            .execute_with_level(send, self.log_level)
             .await
-            .map(|v: Vec<RemindResponse>| v.iter().map(|x| Remind::from(x.clone())).collect())
     }
 
-    fn common_headers(&self) -> anyhow::Result<HeaderMap> {
-        let mut headers = HeaderMap::new();
+    /// POST/PATCH用。全ての送信に同じ `Idempotency-Key` を付け、エラーにもそのキーを載せる。
+    /// `lookup_before_retry` が有効で `lookup` があれば、再送の前にGETして
+    /// 既に登録済みならそのレスポンスを返す
+    async fn execute_mutation<F, R>(
+        &self,
+        key: &IdempotencyKey,
+        lookup: Option<Url>,
+        send: F,
+    ) -> Result<Response>
+    where
+        R: Future<Output = reqwest::Result<Response>>,
+        F: Fn(HeaderMap) -> R,
+    {
+        let mut headers = self.common_headers()?;
+        headers.insert(IDEMPOTENCY_KEY_HEADER, key.as_str().parse()?);
+        let lookup = lookup.filter(|_| self.lookup_before_retry);
+        let attempts = AtomicUsize::new(0);
+
+        self.execute(|| {
+            let headers = headers.clone();
+            let lookup = lookup
+                .clone()
+                .filter(|_| attempts.fetch_add(1, Ordering::SeqCst) > 0);
+            let send = &send;
+            async move {
+                if let Some(url) = lookup {
+                    let found = self.client.get(url).headers(headers.clone()).send().await;
+                    match found {
+                        Ok(res) if res.status().is_success() => {
+                            log::log!(
+                                self.log_level,
+                                "👻 Already registered. skip retry. Idempotency-Key: {}",
+                                headers[IDEMPOTENCY_KEY_HEADER].to_str().unwrap_or_default(),
+                            );
+                            return Ok(res);
+                        }
+                        _ => {}
+                    }
+                }
+                send(headers).await
+            }
+        })
+        .await
+        .map_err(|e| e.with_idempotency_key(key))
+    }
+
+    /// レスポンスに未知の値があれば、strict モードならエラーにし、そうでなければログに残して続ける
+    fn check_unknown<V: UnknownValues>(&self, value: V) -> Result<V> {
+        for (field, v) in value.unknown_values() {
+            if self.strict {
+                return Err(LectoError::UnknownValue { field, value: v });
+            }
+            log::warn!("❓ Unknown value in response. {}: {}", field, v);
+        }
+        Ok(value)
+    }
+
+    fn common_headers(&self) -> Result<HeaderMap> {
+        let mut headers = self.default_headers.clone();
         headers.insert("Authorization", format!("Bearer {}", self.api_key).parse()?);
         Ok(headers)
     }
@@ -155,80 +777,49 @@ impl Client {
     async fn handle_response<T: Debug, V: DeserializeOwned>(
         req: Option<T>,
         res: Response,
-    ) -> anyhow::Result<V> {
+    ) -> Result<V> {
+        let res = Self::error_for_status(req, res).await?;
+        let body = res.text().await?;
+        serde_json::from_str(&body).map_err(|source| LectoError::Decode { source, body })
+    }
+
+    /// bodyを読まずにステータスだけ確認する (DELETEなど)
+    async fn handle_empty_response<T: Debug>(req: Option<T>, res: Response) -> Result<()> {
+        Self::error_for_status(req, res).await.map(|_| ())
+    }
+
+    async fn error_for_status<T: Debug>(req: Option<T>, res: Response) -> Result<Response> {
         let status = res.status();
-        if !status.is_success() {
-            Err(match status {
-                StatusCode::UNPROCESSABLE_ENTITY => LectoError::UnprocessableEntity {
-                    status,
-                    request: format!("{:#?}", req),
-                    response: format!("{:#?}", res.text().await?),
-                }
-                .into(),
-                StatusCode::BAD_REQUEST => LectoError::BadRequest {
-                    status,
-                    request: format!("{:#?}", req),
-                    response: format!("{:#?}", res.text().await?),
-                }
-                .into(),
-                StatusCode::INTERNAL_SERVER_ERROR => LectoError::InternalServerError {
-                    status,
-                    request: format!("{:#?}", req),
-                    response: format!("{:#?}", res.text().await?),
-                }
-                .into(),
-                _ => {
-                    anyhow::anyhow!(
-                        "Something else happened. Status: {:?} Req: {:#?} Res: {}",
-                        status,
-                        req,
-                        res.text().await?
-                    )
-                }
-            })
-        } else {
-            Ok(res.json().await?)
+        if status.is_success() {
+            return Ok(res);
         }
+        let retry_after = parse_retry_after(res.headers());
+        Err(LectoError::from_status(
+            status,
+            format!("{:#?}", req),
+            res.text().await?,
+            retry_after,
+        ))
     }
 }
 
-async fn retry<F, R>(send: F, max_retry: usize) -> anyhow::Result<reqwest::Response>
-where
-    R: core::future::Future<Output = Result<reqwest::Response, reqwest::Error>>,
-    F: Fn() -> R,
-{
-    use tokio::time::sleep;
-
-    let mut attempts = 1;
-    loop {
-        let res = send().await;
-        if attempts == max_retry {
-            return Ok(res?);
-        }
+/// `paginate` がページをまたいで持ち回る状態
+struct PaginationState {
+    next: Option<PageRequest>,
+    /// 直前のページのbodyのハッシュ
+    previous: Option<u64>,
+    requested: HashSet<PageRequest>,
+    seen: HashSet<u64>,
+}
 
-        attempts += 1;
-        match res {
-            Ok(x) => match x.status() {
-                StatusCode::OK | StatusCode::UNPROCESSABLE_ENTITY | StatusCode::BAD_REQUEST => {
-                    return Ok(x);
-                }
-                _ => {
-                    log::error!(
-                        "👻 Reqwest Error! will retry attempts: {}, Error: {:?}",
-                        attempts,
-                        x,
-                    );
-                }
-            },
-            Err(e) => {
-                log::error!(
-                    "👻 Reqwest Error! will retry attempts: {}, Error: {:?}",
-                    attempts,
-                    e
-                );
-            }
+impl Default for PaginationState {
+    fn default() -> Self {
+        Self {
+            next: Some(PageRequest::default()),
+            previous: None,
+            requested: HashSet::new(),
+            seen: HashSet::new(),
         }
-        sleep(Duration::from_millis(1000)).await;
     }
 }
 
@@ -236,11 +827,15 @@ where
 mod tests {
     use super::*;
     use crate::fixture::{
-        self, lecto_debt_response, lecto_debt_status_response, lecto_debtor_response,
+        self, lecto_debt_response, lecto_debt_status_response, lecto_debtor_response, test_client,
     };
+    use crate::Yen;
     use assert_matches::assert_matches;
+    use chrono::{TimeZone, Utc};
     use mockito::Matcher;
+    use reqwest::StatusCode;
     use serde_json::json;
+    use std::time::Duration;
 
     async fn mock_server() -> mockito::ServerGuard {
         mockito::Server::new_async().await
@@ -250,7 +845,7 @@ mod tests {
     async fn test_post_debtor() -> anyhow::Result<()> {
         let mut server = mock_server().await;
         let api_key = "apikey";
-        let client = Client::new(api_key.into(), server.url(), 1, 10);
+        let client = test_client(api_key, server.url());
         let req = fixture::debtor_request_sample_data();
         let raw_req = fixture::debtor_raw_request_sample_data();
         let response_body = lecto_debtor_response();
@@ -273,7 +868,7 @@ mod tests {
     async fn test_post_debtor_validation_error() -> anyhow::Result<()> {
         let mut server = mock_server().await;
         let api_key = "apikey";
-        let client = Client::new(api_key.into(), server.url(), 1, 10);
+        let client = test_client(api_key, server.url());
         let req = fixture::debtor_request_sample_data();
         let raw_req = fixture::debtor_raw_request_sample_data();
         let response_body = serde_json::to_string(&json!({
@@ -289,12 +884,9 @@ mod tests {
             .create();
 
         let res = client.post_debtor(req.clone()).await;
-        assert_matches!(res, Err(e) => {
-            if let Some(LectoError::UnprocessableEntity{status, ..}) = e.downcast_ref::<LectoError>() {
-                assert_eq!(*status, StatusCode::UNPROCESSABLE_ENTITY);
-            } else {
-                panic!("is not LectoError");
-            }
+        assert_matches!(res, Err(LectoError::UnprocessableEntity { status, body, .. }) => {
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+            assert_eq!(body, Some(ErrorResponse { errors: vec!["UnprocessableEntity".into()] }));
         });
 
         mock.assert();
@@ -305,7 +897,7 @@ mod tests {
     async fn test_post_debtor_internal_server_error() -> anyhow::Result<()> {
         let mut server = mock_server().await;
         let api_key = "apikey";
-        let client = Client::new(api_key.into(), server.url(), 1, 10);
+        let client = test_client(api_key, server.url());
         let req = fixture::debtor_request_sample_data();
         let raw_req = fixture::debtor_raw_request_sample_data();
         let response_body = serde_json::to_string(&json!({
@@ -321,12 +913,9 @@ mod tests {
             .create();
 
         let res = client.post_debtor(req.clone()).await;
-        assert_matches!(res, Err(e) => {
-            if let Some(LectoError::InternalServerError{status, ..}) = e.downcast_ref::<LectoError>() {
-                assert_eq!(*status, StatusCode::INTERNAL_SERVER_ERROR);
-            } else {
-                panic!("is not LectoError");
-            }
+        assert_matches!(res, Err(LectoError::InternalServerError { status, body, .. }) => {
+            assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(body, Some(ErrorResponse { errors: vec!["InternalServerError".into()] }));
         });
 
         mock.assert();
@@ -334,32 +923,164 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_post_debtor_reqwest_error() {
-        let api_key = "apikey";
-        let client = Client::new(api_key.into(), "https://awsedfghjk.aiueo".into(), 1, 20);
+    async fn test_post_debtor_validate_before_send() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .validate_before_send(true)
+            .build()?;
+        let mock = server.mock("POST", "/debtors").expect(0).create();
 
         let res = client
-            .post_debtor(fixture::debtor_request_sample_data())
+            .post_debtor(DebtorRequest {
+                email: "invalid".into(),
+                ..fixture::debtor_request_sample_data()
+            })
             .await;
-
-        assert_matches!(res, Err(e) => {
-            assert_matches!(e.downcast_ref::<reqwest::Error>(), Some(_));
+        assert_matches!(res, Err(LectoError::Validation(errors)) => {
+            assert_eq!(errors.errors[0].field, "email");
         });
+
+        mock.assert();
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_post_debt() -> anyhow::Result<()> {
+    async fn test_post_debtor_validates_birth_date_in_business_time_zone() -> anyhow::Result<()> {
         let mut server = mock_server().await;
-        let api_key = "apikey";
-        let client = Client::new(api_key.into(), server.url(), 1, 20);
-        let req = fixture::debt_request_sample_data();
-        let mock = server
-            .mock("POST", "/debts")
-            .with_status(200)
-            .match_header("authorization", format!("Bearer {}", api_key).as_str())
-            .match_body(serde_json::to_string(&req)?.as_str())
-            .with_body(serde_json::to_string(&lecto_debt_response())?)
-            .create();
+        let mock = server.mock("POST", "/debtors").expect(0).create();
+        let ahead = FixedOffset::east_opt(14 * 3600).unwrap();
+        let behind = FixedOffset::west_opt(12 * 3600).unwrap();
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .validate_before_send(true)
+            .business_time_zone(behind)
+            .build()?;
+
+        // UTC+14の今日は、UTC-12ではまだ明日
+        let res = client
+            .post_debtor(DebtorRequest {
+                birth_date: Some(tz::today(&ahead)),
+                ..fixture::debtor_request_sample_data()
+            })
+            .await;
+        assert_matches!(res, Err(LectoError::Validation(errors)) => {
+            assert_eq!(errors.errors[0].field, "birth_date");
+        });
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_debtor_reqwest_error() {
+        let api_key = "apikey";
+        let client = test_client(api_key, "https://awsedfghjk.aiueo".into());
+
+        let res = client
+            .post_debtor(fixture::debtor_request_sample_data())
+            .await;
+
+        assert_matches!(res, Err(LectoError::Transport { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_post_debt_reuses_idempotency_key() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .retry_policy(RetryPolicy::fixed(2, Duration::from_millis(1)))
+            .build()?;
+        let mock = server
+            .mock("POST", "/debts")
+            .match_header(
+                "idempotency-key",
+                Matcher::Regex("^[0-9a-f]{8}-[0-9a-f]{4}-4".into()),
+            )
+            .with_status(503)
+            .expect(2)
+            .create();
+
+        let res = client.post_debt(fixture::debt_request_sample_data()).await;
+        assert_matches!(&res, Err(LectoError::ServerError { .. }));
+        assert!(res.unwrap_err().idempotency_key().is_some());
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_debtor_with_idempotency_key() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let mock = server
+            .mock("POST", "/debtors")
+            .match_header("idempotency-key", "my-key")
+            .with_status(422)
+            .with_body(r#"{"errors": ["UnprocessableEntity"]}"#)
+            .create();
+
+        let res = client
+            .post_debtor_with_idempotency_key(
+                fixture::debtor_request_sample_data(),
+                IdempotencyKey::new("my-key"),
+            )
+            .await;
+        assert_matches!(res, Err(e @ LectoError::UnprocessableEntity { .. }) => {
+            assert_eq!(e.idempotency_key(), Some("my-key"));
+        });
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_debtor_lookup_before_retry() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .retry_policy(RetryPolicy::fixed(3, Duration::from_millis(1)))
+            .lookup_before_retry(true)
+            .build()?;
+        let post = server
+            .mock("POST", "/debtors")
+            .with_status(504)
+            .expect(1)
+            .create();
+        let get = server
+            .mock("GET", "/debtors/test-external-id")
+            .with_status(200)
+            .with_body(serde_json::to_string(&lecto_debtor_response())?)
+            .expect(1)
+            .create();
+
+        let res = client
+            .post_debtor(fixture::debtor_request_sample_data())
+            .await?;
+        assert_eq!(res.debtor_id, "DEBTOR_111");
+
+        post.assert();
+        get.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_debt() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let api_key = "apikey";
+        let client = test_client(api_key, server.url());
+        let req = fixture::debt_request_sample_data();
+        let mock = server
+            .mock("POST", "/debts")
+            .with_status(200)
+            .match_header("authorization", format!("Bearer {}", api_key).as_str())
+            .match_body(serde_json::to_string(&req)?.as_str())
+            .with_body(serde_json::to_string(&lecto_debt_response())?)
+            .create();
 
         let _ = client.post_debt(req).await?;
 
@@ -371,7 +1092,7 @@ mod tests {
     async fn test_post_debt_validation_error() -> anyhow::Result<()> {
         let mut server = mock_server().await;
         let api_key = "apikey";
-        let client = Client::new(api_key.into(), server.url(), 1, 10);
+        let client = test_client(api_key, server.url());
         let req = fixture::debt_request_sample_data();
         let response_body = serde_json::to_string(&json!({
             "errors": ["UnprocessableEntity"],
@@ -386,23 +1107,95 @@ mod tests {
             .create();
 
         let res = client.post_debt(req.clone()).await;
-        assert_matches!(res, Err(e) => {
-            if let Some(LectoError::UnprocessableEntity{status, ..}) = e.downcast_ref::<LectoError>() {
-                assert_eq!(*status, StatusCode::UNPROCESSABLE_ENTITY);
-            } else {
-                panic!("is not LectoError");
-            }
+        assert_matches!(res, Err(LectoError::UnprocessableEntity { status, body, .. }) => {
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+            assert_eq!(body, Some(ErrorResponse { errors: vec!["UnprocessableEntity".into()] }));
         });
 
         mock.assert();
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_debt() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let api_key = "apikey";
+        let client = test_client(api_key, server.url());
+        let mock = server
+            .mock("GET", "/debts/debt%20id")
+            .with_status(200)
+            .match_header("authorization", format!("Bearer {}", api_key).as_str())
+            .with_body(serde_json::to_string(&lecto_debt_response())?)
+            .create();
+
+        let res = client.get_debt("debt id").await?;
+        assert_eq!(res.debt_id, "debt id");
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_debts() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let api_key = "apikey";
+        let client = test_client(api_key, server.url());
+        let filter = DebtFilter {
+            debtor_id: Some("debtor id".into()),
+            status: Some(crate::DebtStatusVariable::Active),
+            repayment_due_from: NaiveDate::from_ymd_opt(2021, 1, 1),
+            repayment_due_to: NaiveDate::from_ymd_opt(2021, 1, 31),
+            ..Default::default()
+        };
+        let mock = server
+            .mock("GET", "/debts")
+            .with_status(200)
+            .match_header("authorization", format!("Bearer {}", api_key).as_str())
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("debtor_id".into(), "debtor id".into()),
+                Matcher::UrlEncoded("status".into(), "active".into()),
+                Matcher::UrlEncoded("repayment_due_from".into(), "2021-01-01".into()),
+                Matcher::UrlEncoded("repayment_due_to".into(), "2021-01-31".into()),
+            ]))
+            .with_body(serde_json::to_string(&json!([lecto_debt_response()]))?)
+            .create();
+
+        let res = client.list_debts(&filter).await?;
+        assert_matches!(&res[..], [Debt { .. }]);
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_patch_debt() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let api_key = "apikey";
+        let client = test_client(api_key, server.url());
+        let req = DebtPatchRequest {
+            debt_fee: Some(Yen::new(300)),
+            remind_segments: Some(vec!["y2022".into()]),
+            ..Default::default()
+        };
+        let mock = server
+            .mock("PATCH", "/debts/debt%20id")
+            .with_status(200)
+            .match_header("authorization", format!("Bearer {}", api_key).as_str())
+            .match_body(serde_json::to_string(&req)?.as_str())
+            .with_body(serde_json::to_string(&lecto_debt_response())?)
+            .create();
+
+        let _ = client.patch_debt("debt id", req).await?;
+
+        mock.assert();
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_patch_debt_status() -> anyhow::Result<()> {
         let mut server = mock_server().await;
         let api_key = "apikey";
-        let client = Client::new(api_key.into(), server.url(), 1, 10);
+        let client = test_client(api_key, server.url());
         let req = fixture::debt_status_request_sample_data();
         let mock = server
             .mock("PATCH", "/debt_statuses")
@@ -422,7 +1215,7 @@ mod tests {
     async fn test_patch_debt_status_validation_error() -> anyhow::Result<()> {
         let mut server = mock_server().await;
         let api_key = "apikey";
-        let client = Client::new(api_key.into(), server.url(), 1, 10);
+        let client = test_client(api_key, server.url());
         let req = fixture::debt_status_request_sample_data();
         let response_body = serde_json::to_string(&json!({
             "errors": ["UnprocessableEntity"],
@@ -437,11 +1230,438 @@ mod tests {
             .create();
 
         let res = client.patch_debt_statuses(req.clone()).await;
+        assert_matches!(res, Err(LectoError::UnprocessableEntity { status, body, .. }) => {
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+            assert_eq!(body, Some(ErrorResponse { errors: vec!["UnprocessableEntity".into()] }));
+        });
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_debt_status_timeline() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let mut active = lecto_debt_response()["debt_status"].clone();
+        active["changed_at"] = json!("2021-01-01T00:00:00+09:00");
+        active["expire_at"] = json!("2021-11-15T12:34:00+09:00");
+        let mut repaid = lecto_debt_status_response();
+        repaid["changed_at"] = json!("2021-11-15T12:34:00+09:00");
+        repaid["expire_at"] = json!("9999-12-31T23:59:59+09:00");
+        let mock = server
+            .mock("GET", "/debt_statuses")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("debt_id".into(), "debt id".into()),
+                Matcher::UrlEncoded("page".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(serde_json::to_string(&json!([repaid, active]))?)
+            .create();
+
+        let timeline = client.debt_status_timeline("debt id").await?;
+        assert_eq!(
+            timeline
+                .statuses()
+                .iter()
+                .map(|s| s.status.clone())
+                .collect::<Vec<_>>(),
+            vec![DebtStatusVariable::Active, DebtStatusVariable::Repaid]
+        );
+        assert!(timeline.is_consistent());
+        assert!(timeline.statuses()[1].is_open_ended());
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transition_debt_status() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let get = server
+            .mock("GET", "/debts/debt%20id")
+            .with_status(200)
+            .with_body(serde_json::to_string(&lecto_debt_response())?)
+            .create();
+        let patch = server
+            .mock("PATCH", "/debt_statuses")
+            .match_body(Matcher::Json(json!({
+                "debt_id": "debt id",
+                "status": "repaid",
+                "changed_at": "2021-11-15T12:34:00+09:00",
+                "expire_at": "9999-12-31T23:59:59+09:00",
+            })))
+            .with_status(200)
+            .with_body(serde_json::to_string(&lecto_debt_status_response())?)
+            .create();
+
+        // 業務タイムゾーン (JST) に直して送る
+        let at = Utc.with_ymd_and_hms(2021, 11, 15, 3, 34, 0).unwrap();
+        let res = client
+            .transition_debt_status("debt id", DebtStatusVariable::Repaid, at)
+            .await?;
+        assert_eq!(res.status, DebtStatusVariable::Repaid);
+
+        get.assert();
+        patch.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transition_debt_status_illegal() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let mut debt = lecto_debt_response();
+        debt["debt_status"]["status"] = json!("repaid");
+        let get = server
+            .mock("GET", "/debts/debt%20id")
+            .with_status(200)
+            .with_body(serde_json::to_string(&debt)?)
+            .create();
+        let patch = server.mock("PATCH", "/debt_statuses").expect(0).create();
+
+        let res = client
+            .transition_debt_status("debt id", DebtStatusVariable::Active, Utc::now())
+            .await;
+        assert_matches!(
+            res,
+            Err(LectoError::IllegalTransition {
+                from: DebtStatusVariable::Repaid,
+                to: DebtStatusVariable::Active,
+                ..
+            })
+        );
+
+        get.assert();
+        patch.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_debtor() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let api_key = "apikey";
+        let client = test_client(api_key, server.url());
+        let mock = server
+            .mock("GET", "/debtors/DEBTOR_111")
+            .with_status(200)
+            .match_header("authorization", format!("Bearer {}", api_key).as_str())
+            .with_body(serde_json::to_string(&lecto_debtor_response())?)
+            .create();
+
+        let res = client.get_debtor("DEBTOR_111").await?;
+        assert_eq!(res.debtor_id, "DEBTOR_111");
+        assert!(res.address.kyc_done.is_done());
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_debtor_not_found() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let mock = server
+            .mock("GET", "/debtors/unknown")
+            .with_status(404)
+            .with_body(r#"{"errors": ["NotFound"]}"#)
+            .create();
+
+        let res = client.get_debtor("unknown").await;
+        assert_matches!(res, Err(LectoError::NotFound { .. }));
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_debtors() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let api_key = "apikey";
+        let client = test_client(api_key, server.url());
+        let filter = DebtorFilter {
+            kyc_done: Some(true),
+            email: Some("sample@example.com".into()),
+            ..Default::default()
+        };
+        let mock = server
+            .mock("GET", "/debtors")
+            .with_status(200)
+            .match_header("authorization", format!("Bearer {}", api_key).as_str())
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("kyc_done".into(), "1".into()),
+                Matcher::UrlEncoded("email".into(), "sample@example.com".into()),
+            ]))
+            .with_body(serde_json::to_string(&json!([lecto_debtor_response()]))?)
+            .create();
+
+        let res = client.list_debtors(&filter).await?;
+        assert_matches!(&res[..], [Debtor { .. }]);
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_patch_debtor() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let api_key = "apikey";
+        let client = test_client(api_key, server.url());
+        let req = DebtorPatchRequest {
+            address: Some("東京都yy区yy町y-y-y".into()),
+            postal_code: Some("1000001".into()),
+            kyc_done: Some(false),
+            ..Default::default()
+        };
+        let mock = server
+            .mock("PATCH", "/debtors/DEBTOR_111")
+            .with_status(200)
+            .match_header("authorization", format!("Bearer {}", api_key).as_str())
+            .match_body(Matcher::JsonString(
+                r#"{"address":"東京都yy区yy町y-y-y","kyc_done":0,"postal_code":"1000001"}"#.into(),
+            ))
+            .with_body(serde_json::to_string(&lecto_debtor_response())?)
+            .create();
+
+        let res = client.patch_debtor("DEBTOR_111", req).await?;
+        assert_matches!(res, Debtor { .. });
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_debtor() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let api_key = "apikey";
+        let client = test_client(api_key, server.url());
+        let mock = server
+            .mock("DELETE", "/debtors/DEBTOR_111")
+            .with_status(204)
+            .match_header("authorization", format!("Bearer {}", api_key).as_str())
+            .create();
+
+        client.delete_debtor("DEBTOR_111").await?;
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_debts_stream_follows_pages() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .retry_policy(RetryPolicy::none())
+            .page_size(2)
+            .build()?;
+        let page1 = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("page".into(), "1".into()),
+                Matcher::UrlEncoded("per_page".into(), "2".into()),
+            ]))
+            .with_status(200)
+            .with_body(serde_json::to_string(&json!([
+                lecto_debt_response(),
+                lecto_debt_response()
+            ]))?)
+            .create();
+        let page2 = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_status(200)
+            .with_header(
+                "link",
+                format!(r#"<{}/debts?cursor=xyz>; rel="next""#, server.url()).as_str(),
+            )
+            .with_body(serde_json::to_string(&json!([lecto_debt_response()]))?)
+            .create();
+        let page3 = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::UrlEncoded("cursor".into(), "xyz".into()))
+            .with_status(200)
+            .with_body(serde_json::to_string(&json!({
+                "data": [lecto_debt_response()],
+                "meta": { "next_cursor": null },
+            }))?)
+            .create();
+
+        let debts = collect_all(client.debts_stream(DebtFilter::default())).await?;
+        assert_eq!(debts.len(), 4);
+
+        page1.assert();
+        page2.assert();
+        page3.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reminds_endpoint_ignoring_page() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .retry_policy(RetryPolicy::none())
+            .page_size(2)
+            .build()?;
+        // `page` に関係なく全件を配列で返す
+        let json = fixture::remind_responses_json();
+        let reminds = server
+            .mock("GET", "/remind_groups/1/reminds")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(json)
+            .expect(1)
+            .create();
+        let debts = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(serde_json::to_string(&json!([
+                lecto_debt_response(),
+                lecto_debt_response()
+            ]))?)
+            .expect(2)
+            .create();
+
+        let remind_at = NaiveDate::from_ymd_opt(2022, 3, 1).unwrap();
+        assert_eq!(client.get_reminds(1, remind_at).await?.len(), 2);
+        // `page` を解釈するはずのエンドポイントでも、同じページが返ってきたら止める
+        assert_eq!(client.list_debts(&DebtFilter::default()).await?.len(), 2);
+
+        reminds.assert();
+        debts.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_debts_follows_relative_link() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let mut other = lecto_debt_response();
+        other["debt_id"] = "other debt id".into();
+        let first = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::UrlEncoded("page".into(), "1".into()))
+            .with_status(200)
+            .with_header("link", r#"</debts?after=abc>; rel="next""#)
+            .with_body(serde_json::to_string(&json!([lecto_debt_response()]))?)
+            .create();
+        let second = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::UrlEncoded("after".into(), "abc".into()))
+            .match_header("authorization", "Bearer apikey")
+            .with_status(200)
+            .with_body(serde_json::to_string(&json!([other]))?)
+            .create();
+
+        assert_eq!(client.list_debts(&DebtFilter::default()).await?.len(), 2);
+        first.assert();
+        second.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_debts_cursor_cycle() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let page = |debt_id: &str, next_cursor: &str| {
+            let mut debt = lecto_debt_response();
+            debt["debt_id"] = debt_id.into();
+            serde_json::to_string(&json!({
+                "data": [debt],
+                "meta": {"next_cursor": next_cursor},
+            }))
+        };
+        let _first = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::UrlEncoded("page".into(), "1".into()))
+            .with_status(200)
+            .with_body(page("debt 1", "a")?)
+            .create();
+        let _a = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::UrlEncoded("cursor".into(), "a".into()))
+            .with_status(200)
+            .with_body(page("debt 2", "b")?)
+            .create();
+        // a -> b -> a と循環する
+        let b = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::UrlEncoded("cursor".into(), "b".into()))
+            .with_status(200)
+            .with_body(page("debt 3", "a")?)
+            .expect(1)
+            .create();
+
+        let res = client.list_debts(&DebtFilter::default()).await;
+        assert_matches!(res, Err(LectoError::Pagination(m)) if m.contains("Cursor(\"a\")"));
+        b.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_debts_rejects_foreign_link() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let mut foreign = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let _first = server
+            .mock("GET", "/debts")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_header(
+                "link",
+                &format!(r#"<{}/debts?page=2>; rel="next""#, foreign.url()),
+            )
+            .with_body(serde_json::to_string(&json!([lecto_debt_response()]))?)
+            .create();
+        // APIキーを送ってしまわないよう、よそのホストには行かない
+        let stolen = foreign
+            .mock("GET", "/debts")
+            .match_query(Matcher::Any)
+            .expect(0)
+            .create();
+
+        let res = client.list_debts(&DebtFilter::default()).await;
+        assert_matches!(res, Err(LectoError::Pagination(_)));
+        stolen.assert();
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[case(401)]
+    #[case(403)]
+    #[case(404)]
+    #[case(409)]
+    #[case(429)]
+    #[case(502)]
+    #[case(503)]
+    #[tokio::test]
+    async fn test_post_debt_error_status(#[case] code: usize) -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let mock = server
+            .mock("POST", "/debts")
+            .with_status(code)
+            .with_header("retry-after", "7")
+            .with_body(r#"{"errors": ["error"]}"#)
+            .create();
+
+        let res = client.post_debt(fixture::debt_request_sample_data()).await;
         assert_matches!(res, Err(e) => {
-            if let Some(LectoError::UnprocessableEntity{status, ..}) = e.downcast_ref::<LectoError>() {
-                assert_eq!(*status, StatusCode::UNPROCESSABLE_ENTITY);
-            } else {
-                panic!("is not LectoError");
+            assert_eq!(e.status(), Some(StatusCode::from_u16(code as u16)?));
+            assert_eq!(e.error_response(), Some(&ErrorResponse { errors: vec!["error".into()] }));
+            match code {
+                401 | 403 => assert!(e.is_auth_error()),
+                404 => assert_matches!(e, LectoError::NotFound { .. }),
+                409 => assert_matches!(e, LectoError::Conflict { .. }),
+                429 => assert_matches!(e, LectoError::TooManyRequests { retry_after, .. } => {
+                    assert_eq!(retry_after, Some(Duration::from_secs(7)));
+                }),
+                _ => assert!(e.is_server_error()),
             }
         });
 
@@ -449,15 +1669,92 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_post_debt_retry_policy() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .retry_policy(RetryPolicy::fixed(3, Duration::from_millis(10)))
+            .build()?;
+        let unavailable = server
+            .mock("POST", "/debts")
+            .with_status(503)
+            .expect(2)
+            .create();
+        let created = server
+            .mock("POST", "/debts")
+            .with_status(201)
+            .with_body(serde_json::to_string(&lecto_debt_response())?)
+            .expect(1)
+            .create();
+
+        let _ = client
+            .post_debt(fixture::debt_request_sample_data())
+            .await?;
+
+        unavailable.assert();
+        created.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_headers() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .user_agent("my-service/1.0")
+            .default_header(
+                reqwest::header::HeaderName::from_static("x-tenant"),
+                reqwest::header::HeaderValue::from_static("tenant-1"),
+            )
+            .http_client(reqwest::Client::new())
+            .build()?;
+        let mock = server
+            .mock("POST", "/debts")
+            .match_header("authorization", "Bearer apikey")
+            .match_header("user-agent", "my-service/1.0")
+            .match_header("x-tenant", "tenant-1")
+            .with_status(200)
+            .with_body(serde_json::to_string(&lecto_debt_response())?)
+            .create();
+
+        let _ = client
+            .post_debt(fixture::debt_request_sample_data())
+            .await?;
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_debt_decode_error() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let mock = server
+            .mock("POST", "/debts")
+            .with_status(200)
+            .with_body(r#"{"id": "not a debt"}"#)
+            .create();
+
+        let res = client.post_debt(fixture::debt_request_sample_data()).await;
+        assert_matches!(res, Err(LectoError::Decode { body, .. }) => {
+            assert_eq!(body, r#"{"id": "not a debt"}"#);
+        });
+
+        mock.assert();
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_reminds() -> anyhow::Result<()> {
         let mut server = mock_server().await;
         let api_key = "apikey";
-        let client = Client::new(api_key.into(), server.url(), 1, 10);
+        let client = test_client(api_key, server.url());
         let remind_group_id = 1;
         let remind_at = NaiveDate::from_ymd_opt(2022, 2, 2).unwrap();
-        let json = std::fs::read_to_string("test-data/lecto-remind-groups-reminds.json")
-            .map_err(|e| dbg!(e))?;
+        let json = fixture::remind_responses_json();
 
         let mock = server
             .mock(
@@ -478,4 +1775,182 @@ mod tests {
         mock.assert();
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_pause_remind_group() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let mock = server
+            .mock("PATCH", "/remind_groups/3")
+            .match_body(Matcher::Json(json!({ "status": "paused" })))
+            .with_status(200)
+            .with_body(
+                json!({
+                    "id": 3,
+                    "name": "督促メール",
+                    "status": "paused",
+                    "remind_segments": [{ "name": "y2021" }],
+                    "schedule": { "days_after_due": [1], "send_at": "10:00:00" },
+                })
+                .to_string(),
+            )
+            .create();
+
+        let group = client.pause_remind_group(3).await?;
+        assert!(!group.is_active());
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_reminds_respects_remind_group_status() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .ignore_remind_group_status(false)
+            .build()?;
+        let mock = server
+            .mock("GET", "/remind_groups/1/reminds")
+            .match_query(Matcher::UrlEncoded(
+                "ignore_remind_group_status".into(),
+                "false".into(),
+            ))
+            .with_status(200)
+            .with_body("[]")
+            .create();
+
+        let reminds = client
+            .get_reminds(1, NaiveDate::from_ymd_opt(2022, 2, 2).unwrap())
+            .await?;
+        assert!(reminds.is_empty());
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_reminds_fans_out_per_day() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let json = fixture::remind_responses_json();
+        let mock = server
+            .mock("GET", "/remind_groups/1/reminds")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::Regex("remind_at=2022-0(2-27|2-28|3-01)".into()),
+                Matcher::UrlEncoded("ignore_remind_group_status".into(), "false".into()),
+                Matcher::UrlEncoded("per_page".into(), "10".into()),
+            ]))
+            .with_status(200)
+            .with_body(json)
+            // 3日分 x 2回
+            .expect(6)
+            .create();
+
+        let query = RemindQuery::between(
+            NaiveDate::from_ymd_opt(2022, 2, 27).unwrap(),
+            NaiveDate::from_ymd_opt(2022, 3, 1).unwrap(),
+        )
+        .with_ignore_remind_group_status(false)
+        .with_page_size(10);
+        let reminds = client.query_reminds(1, &query).await?;
+        assert_eq!(reminds.len(), 2);
+        assert_eq!(reminds[0].debts.len(), 2);
+
+        let reminds = client
+            .query_reminds(1, &query.clone().with_debtor_id("nobody"))
+            .await?;
+        assert!(reminds.is_empty());
+
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_reminds_invalid_range() {
+        let client = test_client("apikey", "http://localhost".into());
+        let query = RemindQuery::between(
+            NaiveDate::from_ymd_opt(2022, 3, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2022, 2, 27).unwrap(),
+        );
+        assert_matches!(
+            client.query_reminds(1, &query).await,
+            Err(LectoError::Validation(_))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_reminds_lenient() -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = test_client("apikey", server.url());
+        let json = fixture::remind_responses_json();
+        let mut items: Vec<Value> = serde_json::from_str(&json)?;
+        items[0]["debtor"]["email"] = Value::Null;
+        let _mock = server
+            .mock("GET", "/remind_groups/1/reminds")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(Value::Array(items.clone()).to_string())
+            .create();
+        let remind_at = NaiveDate::from_ymd_opt(2022, 2, 2).unwrap();
+
+        assert_matches!(
+            client.get_reminds(1, remind_at).await,
+            Err(LectoError::Decode { .. })
+        );
+
+        let res = client.get_reminds_lenient(1, remind_at).await?;
+        assert!(!res.is_complete());
+        assert_eq!(res.reminds.len(), 1);
+        assert_eq!(res.reminds[0].label, items[1]["label"]);
+        assert_matches!(&res.errors[..], [e] => {
+            assert_eq!(e.path, "[0].debtor.email");
+            assert_eq!(e.raw, items[0]);
+        });
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[case(false)]
+    #[case(true)]
+    #[tokio::test]
+    async fn test_get_reminds_unknown_status(#[case] strict: bool) -> anyhow::Result<()> {
+        let mut server = mock_server().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .retry_policy(RetryPolicy::none())
+            .strict(strict)
+            .build()?;
+        let json = fixture::remind_responses_json().replacen(
+            "\"debt_cancelled\"",
+            "\"in_negotiation\"",
+            1,
+        );
+        let _mock = server
+            .mock("GET", "/remind_groups/1/reminds")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(json)
+            .create();
+
+        let res = client
+            .get_reminds(1, NaiveDate::from_ymd_opt(2022, 2, 2).unwrap())
+            .await;
+
+        if strict {
+            assert_matches!(res, Err(LectoError::UnknownValue { field, value }) => {
+                assert_eq!(field, "debts[0].debt_status.status");
+                assert_eq!(value, "in_negotiation");
+            });
+        } else {
+            let reminds = res?;
+            assert_eq!(
+                reminds[0].debts[0].debt_status.status,
+                DebtStatusVariable::Unknown("in_negotiation".into())
+            );
+        }
+        Ok(())
+    }
 }
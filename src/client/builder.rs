@@ -0,0 +1,277 @@
+use std::time::Duration;
+
+use chrono::FixedOffset;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::{Certificate, Proxy};
+
+use crate::client::Client;
+use crate::error::{LectoError, Result};
+use crate::pagination::DEFAULT_PAGE_SIZE;
+use crate::retry::RetryPolicy;
+use crate::tz::JST;
+
+/// `timeout` を指定しなかったときのタイムアウト
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// [`Client`] を組み立てるビルダー。`Client::builder()` から作る
+#[derive(Debug, Default)]
+pub struct ClientBuilder {
+    api_key: Option<String>,
+    base_url: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxies: Vec<Proxy>,
+    root_certificates: Vec<Certificate>,
+    user_agent: Option<String>,
+    default_headers: HeaderMap,
+    http_client: Option<reqwest::Client>,
+    retry_policy: Option<RetryPolicy>,
+    log_level: Option<log::Level>,
+    page_size: Option<usize>,
+    lookup_before_retry: bool,
+    validate_before_send: bool,
+    strict: bool,
+    business_time_zone: Option<FixedOffset>,
+    ignore_remind_group_status: Option<bool>,
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// リクエスト全体 (接続からレスポンスbodyの読み込みまで) のタイムアウト。デフォルトは30秒
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 接続確立までのタイムアウト
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    pub fn add_root_certificate(mut self, cert: Certificate) -> Self {
+        self.root_certificates.push(cert);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// 全リクエストに付与するヘッダ。`Authorization` は `api_key` で上書きされる
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.default_headers.insert(name, value);
+        self
+    }
+
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers.extend(headers);
+        self
+    }
+
+    /// 組み立て済みの `reqwest::Client` を使う。
+    /// タイムアウト・プロキシ・ルート証明書とは同時に指定できない
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// リトライ時のログを出すレベル
+    pub fn log_level(mut self, level: log::Level) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    /// 一覧取得で1リクエストあたりに取得する件数
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = Some(page_size.max(1));
+        self
+    }
+
+    /// サーバーが `Idempotency-Key` に対応していない場合向け。
+    /// `post_debtor` / `post_debt` を再送する前に `debtor_id` / `debt_id` で取得し、
+    /// 既に登録されていれば再送せずにそれを返す
+    pub fn lookup_before_retry(mut self, enabled: bool) -> Self {
+        self.lookup_before_retry = enabled;
+        self
+    }
+
+    /// `post_debtor` / `post_debt` の前に `validate()` を呼び、
+    /// 不正なら送信せずに `LectoError::Validation` を返す
+    pub fn validate_before_send(mut self, enabled: bool) -> Self {
+        self.validate_before_send = enabled;
+        self
+    }
+
+    /// レスポンスに `Unknown` になる値 (知らないステータスなど) があったら
+    /// `LectoError::UnknownValue` を返す。無効 (デフォルト) ならログに残して `Unknown` のまま返す
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.strict = enabled;
+        self
+    }
+
+    /// `remind_at` や支払期日の日付を数えるタイムゾーン。デフォルトはJST
+    pub fn business_time_zone(mut self, tz: FixedOffset) -> Self {
+        self.business_time_zone = Some(tz);
+        self
+    }
+
+    /// リマインド取得でリマインドグループの状態 (停止中など) を無視するか。デフォルトは `true`
+    pub fn ignore_remind_group_status(mut self, ignore: bool) -> Self {
+        self.ignore_remind_group_status = Some(ignore);
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let api_key = self
+            .api_key
+            .ok_or_else(|| LectoError::Config("api_key is required".into()))?;
+        let base_url = self
+            .base_url
+            .ok_or_else(|| LectoError::Config("base_url is required".into()))?;
+        url::Url::parse(&base_url)?;
+
+        let mut default_headers = self.default_headers;
+        let user_agent = self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+        default_headers.insert(USER_AGENT, user_agent.parse()?);
+
+        let client = match self.http_client {
+            Some(client) => {
+                if self.timeout.is_some()
+                    || self.connect_timeout.is_some()
+                    || !self.proxies.is_empty()
+                    || !self.root_certificates.is_empty()
+                {
+                    return Err(LectoError::Config(
+                        "http_client cannot be combined with timeout, connect_timeout, proxy or root certificates".into(),
+                    ));
+                }
+                client
+            }
+            None => {
+                let mut builder =
+                    reqwest::Client::builder().timeout(self.timeout.unwrap_or(DEFAULT_TIMEOUT));
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                for proxy in self.proxies {
+                    builder = builder.proxy(proxy);
+                }
+                for cert in self.root_certificates {
+                    builder = builder.add_root_certificate(cert);
+                }
+                builder.build().map_err(LectoError::Builder)?
+            }
+        };
+
+        Ok(Client {
+            api_key,
+            base_url,
+            client,
+            default_headers,
+            retry_policy: self.retry_policy.unwrap_or_default(),
+            log_level: self.log_level.unwrap_or(log::Level::Error),
+            page_size: self.page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+            lookup_before_retry: self.lookup_before_retry,
+            validate_before_send: self.validate_before_send,
+            strict: self.strict,
+            business_time_zone: self.business_time_zone.unwrap_or(JST),
+            ignore_remind_group_status: self.ignore_remind_group_status.unwrap_or(true),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn test_build() -> anyhow::Result<()> {
+        let client = ClientBuilder::new()
+            .api_key("apikey")
+            .base_url("https://example.com/api/v1")
+            .timeout(Duration::from_secs(10))
+            .connect_timeout(Duration::from_secs(1))
+            .proxy(Proxy::all("http://proxy.example.com:8080")?)
+            .user_agent("my-service/1.0")
+            .retry_policy(RetryPolicy::none())
+            .log_level(log::Level::Warn)
+            .build()?;
+
+        assert_eq!(
+            client.default_headers.get(USER_AGENT).unwrap(),
+            "my-service/1.0"
+        );
+        assert_eq!(client.retry_policy, RetryPolicy::none());
+        assert_eq!(client.log_level, log::Level::Warn);
+        assert_eq!(client.business_time_zone(), JST);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_business_time_zone() -> anyhow::Result<()> {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let client = ClientBuilder::new()
+            .api_key("apikey")
+            .base_url("https://example.com/api/v1")
+            .business_time_zone(utc)
+            .build()?;
+        assert_eq!(client.business_time_zone(), utc);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_missing_api_key() {
+        let res = ClientBuilder::new()
+            .base_url("https://example.com/api/v1")
+            .build();
+        assert_matches!(res, Err(LectoError::Config(_)));
+    }
+
+    #[test]
+    fn test_build_invalid_base_url() {
+        let res = ClientBuilder::new()
+            .api_key("apikey")
+            .base_url("not a url")
+            .build();
+        assert_matches!(res, Err(LectoError::InvalidUrl(_)));
+    }
+
+    #[test]
+    fn test_build_http_client_conflict() {
+        let res = ClientBuilder::new()
+            .api_key("apikey")
+            .base_url("https://example.com/api/v1")
+            .http_client(reqwest::Client::new())
+            .timeout(Duration::from_secs(1))
+            .build();
+        assert_matches!(res, Err(LectoError::Config(_)));
+    }
+}
@@ -0,0 +1,461 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+
+use futures::{stream, StreamExt};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::client::Client;
+use crate::debtor::{DebtorRawRequest, DebtorResponse};
+use crate::error::{LectoError, Result};
+use crate::idempotency::IdempotencyKey;
+use crate::strict::UnknownValues;
+use crate::util::join_url;
+use crate::validation::ValidationErrors;
+use crate::{Debt, DebtRequest, Debtor, DebtorRequest};
+
+pub const DEFAULT_BULK_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkOptions {
+    concurrency: usize,
+    endpoint: Option<BulkEndpoint>,
+}
+
+/// まとめて登録できるエンドポイント。
+/// リクエストは配列で送り、レスポンスは同じ順序の配列で、要素は登録結果か `{"errors": [...]}` を想定する
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkEndpoint {
+    pub path: Vec<String>,
+    pub chunk_size: usize,
+}
+
+impl Default for BulkOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_BULK_CONCURRENCY,
+            endpoint: None,
+        }
+    }
+}
+
+impl BulkOptions {
+    /// 同時に投げるリクエスト数 (バルクエンドポイントを使う場合はチャンク数)
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// `path` (例: `["debtors", "bulk"]`) に `chunk_size` 件ずつまとめて送る
+    pub fn with_bulk_endpoint<T: AsRef<str>>(mut self, path: &[T], chunk_size: usize) -> Self {
+        self.endpoint = Some(BulkEndpoint {
+            path: path.iter().map(|p| p.as_ref().to_string()).collect(),
+            chunk_size: chunk_size.max(1),
+        });
+        self
+    }
+}
+
+/// 1件ごとの結果。`index` は入力の順番、`id` は `debtor_id` / `debt_id`
+#[derive(Debug)]
+pub struct BulkItem<T> {
+    pub index: usize,
+    pub id: String,
+    pub result: Result<T>,
+}
+
+#[derive(Debug)]
+pub struct BulkReport<T> {
+    pub items: Vec<BulkItem<T>>,
+}
+
+impl<T> BulkReport<T> {
+    pub fn succeeded(&self) -> impl Iterator<Item = (&BulkItem<T>, &T)> {
+        self.items
+            .iter()
+            .filter_map(|item| item.result.as_ref().ok().map(|v| (item, v)))
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = (&BulkItem<T>, &LectoError)> {
+        self.items
+            .iter()
+            .filter_map(|item| item.result.as_ref().err().map(|e| (item, e)))
+    }
+
+    pub fn is_all_success(&self) -> bool {
+        self.items.iter().all(|item| item.result.is_ok())
+    }
+}
+
+impl Client {
+    /// `post_debtor` を並列に実行し、失敗しても残りを続ける
+    pub async fn bulk_upsert_debtors<I>(&self, reqs: I, options: &BulkOptions) -> BulkReport<Debtor>
+    where
+        I: IntoIterator<Item = DebtorRequest>,
+    {
+        let reqs: Vec<(String, DebtorRequest)> = reqs
+            .into_iter()
+            .map(|req| (req.debtor_id.clone(), req))
+            .collect();
+
+        match &options.endpoint {
+            Some(endpoint) => {
+                self.bulk_chunked(
+                    reqs,
+                    endpoint,
+                    options.concurrency,
+                    |req: &DebtorRequest| req.validate_at(self.today()),
+                    DebtorRawRequest::from,
+                    Debtor::from as fn(DebtorResponse) -> Debtor,
+                )
+                .await
+            }
+            None => {
+                self.bulk_each(reqs, options.concurrency, |req| self.post_debtor(req))
+                    .await
+            }
+        }
+    }
+
+    /// `post_debt` を並列に実行し、失敗しても残りを続ける
+    pub async fn bulk_upsert_debts<I>(&self, reqs: I, options: &BulkOptions) -> BulkReport<Debt>
+    where
+        I: IntoIterator<Item = DebtRequest>,
+    {
+        let reqs: Vec<(String, DebtRequest)> = reqs
+            .into_iter()
+            .map(|req| (req.debt_id.clone(), req))
+            .collect();
+
+        match &options.endpoint {
+            Some(endpoint) => {
+                self.bulk_chunked(
+                    reqs,
+                    endpoint,
+                    options.concurrency,
+                    DebtRequest::validate,
+                    |v| v,
+                    |v: Debt| v,
+                )
+                .await
+            }
+            None => {
+                self.bulk_each(reqs, options.concurrency, |req| self.post_debt(req))
+                    .await
+            }
+        }
+    }
+
+    async fn bulk_each<R, T, F, Fut>(
+        &self,
+        reqs: Vec<(String, R)>,
+        concurrency: usize,
+        send: F,
+    ) -> BulkReport<T>
+    where
+        F: Fn(R) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let items = stream::iter(reqs.into_iter().enumerate())
+            .map(|(index, (id, req))| {
+                let fut = send(req);
+                async move {
+                    BulkItem {
+                        index,
+                        id,
+                        result: fut.await,
+                    }
+                }
+            })
+            .buffered(concurrency)
+            .collect()
+            .await;
+
+        BulkReport { items }
+    }
+
+    /// `validate_before_send` が有効なら、送る前に `validate` で弾いたものはチャンクに入れず
+    /// 1件ずつ `LectoError::Validation` にする
+    async fn bulk_chunked<R, Raw, V, T>(
+        &self,
+        reqs: Vec<(String, R)>,
+        endpoint: &BulkEndpoint,
+        concurrency: usize,
+        validate: impl Fn(&R) -> std::result::Result<(), ValidationErrors>,
+        to_raw: fn(R) -> Raw,
+        convert: fn(V) -> T,
+    ) -> BulkReport<T>
+    where
+        Raw: Serialize + Debug,
+        V: DeserializeOwned + UnknownValues,
+    {
+        let mut rejected: Vec<BulkItem<T>> = vec![];
+        let mut chunks: Vec<Vec<(usize, String, Raw)>> = vec![];
+        let mut valid = 0;
+        for (index, (id, req)) in reqs.into_iter().enumerate() {
+            if self.validate_before_send {
+                if let Err(e) = validate(&req) {
+                    rejected.push(BulkItem {
+                        index,
+                        id,
+                        result: Err(e.into()),
+                    });
+                    continue;
+                }
+            }
+            if valid % endpoint.chunk_size == 0 {
+                chunks.push(Vec::with_capacity(endpoint.chunk_size));
+            }
+            valid += 1;
+            if let Some(chunk) = chunks.last_mut() {
+                chunk.push((index, id, to_raw(req)));
+            }
+        }
+
+        let reports: Vec<Vec<BulkItem<T>>> = stream::iter(chunks)
+            .map(|chunk| async move {
+                let body: Vec<&Raw> = chunk.iter().map(|(_, _, raw)| raw).collect();
+                match self.post_chunk::<_, V>(&endpoint.path, &body).await {
+                    Ok(results) => chunk
+                        .into_iter()
+                        .zip(results)
+                        .map(|((index, id, _), result)| BulkItem {
+                            index,
+                            id,
+                            result: result.and_then(|v| self.check_unknown(v)).map(convert),
+                        })
+                        .collect(),
+                    Err(e) => {
+                        let e = Arc::new(e);
+                        chunk
+                            .into_iter()
+                            .map(|(index, id, _)| BulkItem {
+                                index,
+                                id,
+                                result: Err(LectoError::BulkChunkFailed(e.clone())),
+                            })
+                            .collect()
+                    }
+                }
+            })
+            .buffered(concurrency)
+            .collect()
+            .await;
+
+        let mut items: Vec<BulkItem<T>> = reports.into_iter().flatten().chain(rejected).collect();
+        items.sort_by_key(|item| item.index);
+        BulkReport { items }
+    }
+
+    async fn post_chunk<Raw: Serialize + Debug, V: DeserializeOwned>(
+        &self,
+        path: &[String],
+        body: &[&Raw],
+    ) -> Result<Vec<Result<V>>> {
+        let url = join_url(&self.base_url, path)?;
+        let key = IdempotencyKey::generate();
+
+        let res = self
+            .execute_mutation(&key, None, |headers| {
+                self.client
+                    .post(url.clone())
+                    .json(body)
+                    .headers(headers)
+                    .send()
+            })
+            .await?;
+
+        let values: Vec<Value> = Self::handle_response(Some(body), res)
+            .await
+            .map_err(|e| e.with_idempotency_key(&key))?;
+        if values.len() != body.len() {
+            return Err(LectoError::Decode {
+                source: serde::de::Error::invalid_length(
+                    values.len(),
+                    &format!("{} results", body.len()).as_str(),
+                ),
+                body: Value::Array(values).to_string(),
+            });
+        }
+
+        Ok(values
+            .into_iter()
+            .zip(body)
+            .map(|(value, req)| decode_bulk_item(value, req))
+            .collect())
+    }
+}
+
+fn decode_bulk_item<Raw: Debug, V: DeserializeOwned>(value: Value, req: &Raw) -> Result<V> {
+    if value.get("errors").is_some() && value.get("id").is_none() {
+        // 要素ごとのエラーはバリデーションエラーとして扱う
+        return Err(LectoError::from_status(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("{:#?}", req),
+            value.to_string(),
+            None,
+        ));
+    }
+    serde_json::from_value(value.clone()).map_err(|source| LectoError::Decode {
+        source,
+        body: value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{self, lecto_debt_response, lecto_debtor_response, test_client};
+    use crate::RetryPolicy;
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_bulk_upsert_debts_each() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let client = test_client("apikey", server.url());
+        let ok = server
+            .mock("POST", "/debts")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"debt_id": "ok"}"#.into(),
+            ))
+            .with_status(200)
+            .with_body(serde_json::to_string(&lecto_debt_response())?)
+            .expect(2)
+            .create();
+        let ng = server
+            .mock("POST", "/debts")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"debt_id": "ng"}"#.into(),
+            ))
+            .with_status(422)
+            .with_body(r#"{"errors": ["invalid"]}"#)
+            .expect(1)
+            .create();
+
+        let reqs = ["ok", "ng", "ok"].map(|id| DebtRequest {
+            debt_id: id.into(),
+            ..fixture::debt_request_sample_data()
+        });
+        let report = client
+            .bulk_upsert_debts(reqs, &BulkOptions::default().with_concurrency(2))
+            .await;
+
+        assert_eq!(report.items.len(), 3);
+        assert_eq!(
+            report.items.iter().map(|i| i.index).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(report.succeeded().count(), 2);
+        assert_matches!(&report.failed().collect::<Vec<_>>()[..], [(item, LectoError::UnprocessableEntity { .. })] => {
+            assert_eq!(item.id, "ng");
+        });
+        assert!(!report.is_all_success());
+
+        ok.assert();
+        ng.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_upsert_debtors_endpoint() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let client = test_client("apikey", server.url());
+        let first = server
+            .mock("POST", "/debtors/bulk")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"[{"debtor_id": "d-0", "kyc_done": 1}, {"debtor_id": "d-1"}]"#.into(),
+            ))
+            .with_status(200)
+            .with_body(serde_json::to_string(&json!([
+                lecto_debtor_response(),
+                { "errors": ["email is invalid"] },
+            ]))?)
+            .create();
+        let second = server
+            .mock("POST", "/debtors/bulk")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"[{"debtor_id": "d-2"}]"#.into(),
+            ))
+            .with_status(500)
+            .with_body(r#"{"errors": ["InternalServerError"]}"#)
+            .create();
+
+        let reqs = (0..3).map(|i| DebtorRequest {
+            debtor_id: format!("d-{}", i),
+            ..fixture::debtor_request_sample_data()
+        });
+        let report = client
+            .bulk_upsert_debtors(
+                reqs,
+                &BulkOptions::default().with_bulk_endpoint(&["debtors", "bulk"], 2),
+            )
+            .await;
+
+        assert_matches!(&report.items[..], [a, b, c] => {
+            assert_matches!(a.result, Ok(Debtor { .. }));
+            assert_matches!(b.result, Err(LectoError::UnprocessableEntity { .. }));
+            assert_matches!(&c.result, Err(LectoError::BulkChunkFailed(e)) => {
+                assert!(e.is_server_error());
+            });
+        });
+
+        first.assert();
+        second.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_upsert_debtors_endpoint_validate_before_send() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let client = Client::builder()
+            .api_key("apikey")
+            .base_url(server.url())
+            .retry_policy(RetryPolicy::none())
+            .validate_before_send(true)
+            .build()?;
+        let mock = server
+            .mock("POST", "/debtors/bulk")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"[{"debtor_id": "d-0"}, {"debtor_id": "d-2"}]"#.into(),
+            ))
+            .with_status(200)
+            .with_body(serde_json::to_string(&json!([
+                lecto_debtor_response(),
+                lecto_debtor_response(),
+            ]))?)
+            .expect(1)
+            .create();
+
+        let reqs = (0..3).map(|i| DebtorRequest {
+            debtor_id: format!("d-{}", i),
+            email: if i == 1 {
+                "invalid".into()
+            } else {
+                "sample@example.com".into()
+            },
+            ..fixture::debtor_request_sample_data()
+        });
+        let report = client
+            .bulk_upsert_debtors(
+                reqs,
+                &BulkOptions::default().with_bulk_endpoint(&["debtors", "bulk"], 2),
+            )
+            .await;
+
+        assert_matches!(&report.items[..], [a, b, c] => {
+            assert_matches!(a.result, Ok(Debtor { .. }));
+            assert_eq!(b.id, "d-1");
+            assert_matches!(&b.result, Err(LectoError::Validation(e)) => {
+                assert!(e.field("email").next().is_some());
+            });
+            assert_matches!(c.result, Ok(Debtor { .. }));
+        });
+        mock.assert();
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+//! `custom_fields` (文字列のマップ) と利用側の型との変換。
+//!
+//! 型のフィールドがそのままキーになる。数値・真偽値・日付・unit variantのenumは文字列との間で変換し、
+//! `Option` の `None` はキーごと省く (読み込み時はキーがないか空文字なら `None`)。
+//! ネストした構造体や配列は扱えない
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde::Serialize;
+use serde_json::Value;
+
+/// 変換に失敗した項目
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("custom_fields.{field}: {message}")]
+pub struct CustomFieldError {
+    /// キー名。型全体の問題なら空文字
+    pub field: String,
+    pub message: String,
+}
+
+impl CustomFieldError {
+    fn new(field: impl Into<String>, message: impl ToString) -> Self {
+        Self {
+            field: field.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// `value` を `custom_fields` のマップにする
+pub fn to_map<T: Serialize>(value: &T) -> Result<HashMap<String, String>, CustomFieldError> {
+    let value = serde_json::to_value(value).map_err(|e| CustomFieldError::new("", e))?;
+    let Value::Object(object) = value else {
+        return Err(CustomFieldError::new("", "must be a struct or map"));
+    };
+
+    let mut map = HashMap::with_capacity(object.len());
+    for (field, value) in object {
+        let value = match value {
+            Value::Null => continue,
+            Value::String(s) => s,
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Array(_) | Value::Object(_) => {
+                return Err(CustomFieldError::new(
+                    field,
+                    "nested value is not supported",
+                ))
+            }
+        };
+        map.insert(field, value);
+    }
+    Ok(map)
+}
+
+/// `custom_fields` のマップから `T` を読み込む
+pub fn from_map<T: DeserializeOwned>(map: &HashMap<String, String>) -> Result<T, CustomFieldError> {
+    let deserializer = de::value::MapDeserializer::<_, de::value::Error>::new(
+        map.iter()
+            .map(|(k, v)| (k.as_str(), FieldDeserializer { value: v.as_str() })),
+    );
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let field = match e.path().to_string() {
+            path if path == "." => String::new(),
+            path => path,
+        };
+        CustomFieldError::new(field, e.into_inner())
+    })
+}
+
+/// 文字列1つを、読み込み先の型に合わせて解釈する
+struct FieldDeserializer<'de> {
+    value: &'de str,
+}
+
+impl<'de> IntoDeserializer<'de, de::value::Error> for FieldDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match self.value.trim().parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(e) => Err(de::Error::custom(Invalid(self.value, e))),
+                }
+            }
+        )*
+    };
+}
+
+struct Invalid<'a, E>(&'a str, E);
+
+impl<E: fmt::Display> fmt::Display for Invalid<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid value {:?}: {}", self.0, self.1)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for FieldDeserializer<'de> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.value)
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::value::BorrowedStrDeserializer::new(self.value)
+            .deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Plan {
+        Monthly,
+        Yearly,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Fields {
+        item_name: String,
+        total_amount: i64,
+        elapsed_month: i32,
+        rate: f64,
+        auto_renewal: bool,
+        contracted_on: NaiveDate,
+        plan: Plan,
+        note: Option<String>,
+    }
+
+    fn sample_map() -> HashMap<String, String> {
+        [
+            ("item_name", "iPhoneSE 12"),
+            ("total_amount", "17000"),
+            ("elapsed_month", "-1"),
+            ("rate", "14.6"),
+            ("auto_renewal", "true"),
+            ("contracted_on", "2021-12-01"),
+            ("plan", "monthly"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    }
+
+    fn sample_fields() -> Fields {
+        Fields {
+            item_name: "iPhoneSE 12".into(),
+            total_amount: 17000,
+            elapsed_month: -1,
+            rate: 14.6,
+            auto_renewal: true,
+            contracted_on: NaiveDate::from_ymd_opt(2021, 12, 1).unwrap(),
+            plan: Plan::Monthly,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() -> anyhow::Result<()> {
+        assert_eq!(to_map(&sample_fields())?, sample_map());
+        assert_eq!(from_map::<Fields>(&sample_map())?, sample_fields());
+
+        let mut map = sample_map();
+        map.insert("note".into(), "分割払い".into());
+        map.insert("unused".into(), "ignored".into());
+        assert_eq!(from_map::<Fields>(&map)?.note, Some("分割払い".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_map_names_field() {
+        let mut map = sample_map();
+        map.insert("total_amount".into(), "17,000".into());
+        assert_matches!(from_map::<Fields>(&map), Err(e) => {
+            assert_eq!(e.field, "total_amount");
+            assert!(e.message.contains("17,000"), "{}", e.message);
+        });
+
+        map = sample_map();
+        map.insert("plan".into(), "weekly".into());
+        assert_matches!(from_map::<Fields>(&map), Err(e) if e.field == "plan");
+
+        map = sample_map();
+        map.remove("item_name");
+        assert_matches!(from_map::<Fields>(&map), Err(e) => {
+            assert!(e.message.contains("item_name"), "{}", e.message);
+        });
+    }
+
+    #[test]
+    fn test_to_map_rejects_nested() {
+        #[derive(Serialize)]
+        struct Nested {
+            items: Vec<String>,
+        }
+        assert_matches!(
+            to_map(&Nested { items: vec![] }),
+            Err(e) if e.field == "items"
+        );
+        assert_matches!(to_map(&"string"), Err(e) if e.field.is_empty());
+    }
+}
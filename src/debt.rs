@@ -3,21 +3,25 @@ use std::{
     fmt::Debug,
 };
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize, Serializer};
 
-use crate::{DebtStatus, DebtStatusRequest};
+use crate::custom_fields::{self, CustomFieldError};
+use crate::money::Yen;
+use crate::validation::{self, FieldErrorKind, ValidationErrors};
+use crate::{DebtStatus, DebtStatusRequest, DebtStatusVariable};
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Debt {
     pub id: u64,
     pub debt_id: String,
     pub debtor_id: String,
-    pub dealt_at: DateTime<Local>,
-    pub debt_amount: i64,
-    pub debt_fee: Option<i64>,
-    pub debt_delinquency_charge: Option<i64>,
-    pub repayment_due_at: DateTime<Local>,
+    pub dealt_at: DateTime<FixedOffset>,
+    pub debt_amount: Yen,
+    pub debt_fee: Option<Yen>,
+    pub debt_delinquency_charge: Option<Yen>,
+    pub repayment_due_at: DateTime<FixedOffset>,
     #[serde(default)]
     pub custom_fields: HashMap<String, String>,
     pub remind_segments: Vec<Segment>,
@@ -26,23 +30,53 @@ pub struct Debt {
     pub debt_status: DebtStatus,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+impl Debt {
+    /// 債権額・手数料・遅延損害金の合計。オーバーフローしたら `None`
+    pub fn total_outstanding(&self) -> Option<Yen> {
+        Yen::checked_sum(
+            [
+                Some(self.debt_amount),
+                self.debt_fee,
+                self.debt_delinquency_charge,
+            ]
+            .into_iter()
+            .flatten(),
+        )
+    }
+
+    /// `tz` での支払期日
+    pub fn repayment_due_date(&self, tz: &FixedOffset) -> NaiveDate {
+        self.repayment_due_at.with_timezone(tz).date_naive()
+    }
+
+    /// `tz` での日付 `today` 時点で支払期日を過ぎているか
+    pub fn is_overdue(&self, today: NaiveDate, tz: &FixedOffset) -> bool {
+        self.repayment_due_date(tz) < today
+    }
+
+    /// `custom_fields` を `T` として読み込む
+    pub fn custom_fields_as<T: DeserializeOwned>(&self) -> Result<T, CustomFieldError> {
+        custom_fields::from_map(&self.custom_fields)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Segment {
     pub name: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DebtRequest {
     pub debt_id: String,
     pub debtor_id: String,
-    pub dealt_at: DateTime<Local>,
-    pub debt_amount: i64,
+    pub dealt_at: DateTime<FixedOffset>,
+    pub debt_amount: Yen,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub debt_fee: Option<i64>,
+    pub debt_fee: Option<Yen>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub debt_delinquency_charge: Option<i64>,
-    pub repayment_due_at: DateTime<Local>,
-    #[serde(serialize_with = "ordered_map")]
+    pub debt_delinquency_charge: Option<Yen>,
+    pub repayment_due_at: DateTime<FixedOffset>,
+    #[serde(default, serialize_with = "ordered_map")]
     pub custom_fields: HashMap<String, String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub remind_segments: Option<Vec<String>>,
@@ -52,6 +86,208 @@ pub struct DebtRequest {
     pub debt_status: Option<DebtStatusRequest>,
 }
 
+impl DebtRequest {
+    /// 必須項目を受け取り、`build()` で `validate()` まで行うビルダー
+    pub fn builder(debt_id: impl Into<String>, debtor_id: impl Into<String>) -> DebtRequestBuilder {
+        DebtRequestBuilder::new(debt_id, debtor_id)
+    }
+
+    /// `custom_fields` を `T` として読み込む
+    pub fn custom_fields_as<T: DeserializeOwned>(&self) -> Result<T, CustomFieldError> {
+        custom_fields::from_map(&self.custom_fields)
+    }
+
+    /// `fields` の各フィールドを `custom_fields` に入れる。既存の他のキーは残す
+    pub fn set_custom_fields<T: Serialize>(&mut self, fields: &T) -> Result<(), CustomFieldError> {
+        self.custom_fields.extend(custom_fields::to_map(fields)?);
+        Ok(())
+    }
+
+    /// 金額が0以上か、支払期日が取引日以降か、`debt_status.debt_id` が `debt_id` と一致するか、
+    /// セグメント名が空でないかを確認し、違反をすべて返す
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        for (field, value) in [("debt_id", &self.debt_id), ("debtor_id", &self.debtor_id)] {
+            if validation::is_blank(value) {
+                errors.push(field, FieldErrorKind::Required, "is required");
+            }
+        }
+
+        for (field, value) in [
+            ("debt_amount", Some(self.debt_amount)),
+            ("debt_fee", self.debt_fee),
+            ("debt_delinquency_charge", self.debt_delinquency_charge),
+        ] {
+            if value.is_some_and(Yen::is_negative) {
+                errors.push(field, FieldErrorKind::OutOfRange, "must not be negative");
+            }
+        }
+
+        // 取引日のオフセットで日付を比べる
+        let due = self.repayment_due_at.with_timezone(self.dealt_at.offset());
+        if due.date_naive() < self.dealt_at.date_naive() {
+            errors.push(
+                "repayment_due_at",
+                FieldErrorKind::OutOfRange,
+                "must be on or after dealt_at",
+            );
+        }
+
+        if let Some(debt_status) = &self.debt_status {
+            if debt_status.debt_id != self.debt_id {
+                errors.push(
+                    "debt_status.debt_id",
+                    FieldErrorKind::Mismatch,
+                    format!(
+                        "{:?} does not match debt_id {:?}",
+                        debt_status.debt_id, self.debt_id
+                    ),
+                );
+            }
+        }
+
+        for (i, segment) in self.remind_segments.iter().flatten().enumerate() {
+            if validation::is_blank(segment) {
+                errors.push(
+                    format!("remind_segments[{}]", i),
+                    FieldErrorKind::Required,
+                    "must not be empty",
+                );
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+/// [`DebtRequest`] を組み立てるビルダー。`DebtRequest::builder()` から作る
+#[derive(Debug, Clone, Default)]
+pub struct DebtRequestBuilder {
+    debt_id: String,
+    debtor_id: String,
+    dealt_at: Option<DateTime<FixedOffset>>,
+    debt_amount: Option<Yen>,
+    debt_fee: Option<Yen>,
+    debt_delinquency_charge: Option<Yen>,
+    repayment_due_at: Option<DateTime<FixedOffset>>,
+    custom_fields: HashMap<String, String>,
+    custom_field_errors: Vec<CustomFieldError>,
+    remind_segments: Option<Vec<String>>,
+    partner: Option<PartnerRequest>,
+    debt_status: Option<DebtStatusRequest>,
+}
+
+impl DebtRequestBuilder {
+    pub fn new(debt_id: impl Into<String>, debtor_id: impl Into<String>) -> Self {
+        Self {
+            debt_id: debt_id.into(),
+            debtor_id: debtor_id.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn dealt_at(mut self, dealt_at: impl Into<DateTime<FixedOffset>>) -> Self {
+        self.dealt_at = Some(dealt_at.into());
+        self
+    }
+
+    pub fn debt_amount(mut self, amount: impl Into<Yen>) -> Self {
+        self.debt_amount = Some(amount.into());
+        self
+    }
+
+    pub fn debt_fee(mut self, fee: impl Into<Yen>) -> Self {
+        self.debt_fee = Some(fee.into());
+        self
+    }
+
+    pub fn debt_delinquency_charge(mut self, charge: impl Into<Yen>) -> Self {
+        self.debt_delinquency_charge = Some(charge.into());
+        self
+    }
+
+    pub fn repayment_due_at(mut self, repayment_due_at: impl Into<DateTime<FixedOffset>>) -> Self {
+        self.repayment_due_at = Some(repayment_due_at.into());
+        self
+    }
+
+    pub fn custom_field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.custom_fields.insert(key.into(), value.into());
+        self
+    }
+
+    /// `fields` の各フィールドを `custom_fields` に入れる。
+    /// 変換できなかった場合は `build()` で `custom_fields.{キー名}` のエラーになる
+    pub fn custom_fields<T: Serialize>(mut self, fields: &T) -> Self {
+        match custom_fields::to_map(fields) {
+            Ok(map) => self.custom_fields.extend(map),
+            Err(e) => self.custom_field_errors.push(e),
+        }
+        self
+    }
+
+    pub fn remind_segment(mut self, segment: impl Into<String>) -> Self {
+        self.remind_segments
+            .get_or_insert_with(Vec::new)
+            .push(segment.into());
+        self
+    }
+
+    pub fn partner(mut self, partner: PartnerRequest) -> Self {
+        self.partner = Some(partner);
+        self
+    }
+
+    pub fn debt_status(mut self, debt_status: DebtStatusRequest) -> Self {
+        self.debt_status = Some(debt_status);
+        self
+    }
+
+    pub fn build(self) -> Result<DebtRequest, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        for e in &self.custom_field_errors {
+            errors.push(
+                format!("custom_fields.{}", e.field),
+                FieldErrorKind::InvalidFormat,
+                &e.message,
+            );
+        }
+        if self.dealt_at.is_none() {
+            errors.push("dealt_at", FieldErrorKind::Required, "is required");
+        }
+        if self.debt_amount.is_none() {
+            errors.push("debt_amount", FieldErrorKind::Required, "is required");
+        }
+        if self.repayment_due_at.is_none() {
+            errors.push("repayment_due_at", FieldErrorKind::Required, "is required");
+        }
+        let (Some(dealt_at), Some(debt_amount), Some(repayment_due_at)) =
+            (self.dealt_at, self.debt_amount, self.repayment_due_at)
+        else {
+            return Err(errors);
+        };
+
+        let req = DebtRequest {
+            debt_id: self.debt_id,
+            debtor_id: self.debtor_id,
+            dealt_at,
+            debt_amount,
+            debt_fee: self.debt_fee,
+            debt_delinquency_charge: self.debt_delinquency_charge,
+            repayment_due_at,
+            custom_fields: self.custom_fields,
+            remind_segments: self.remind_segments,
+            partner: self.partner,
+            debt_status: self.debt_status,
+        };
+        if let Err(e) = req.validate() {
+            errors.errors.extend(e.errors);
+        }
+        errors.into_result()?;
+        Ok(req)
+    }
+}
+
 fn ordered_map<S>(value: &HashMap<String, String>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -62,6 +298,56 @@ where
         .serialize(serializer)
 }
 
+/// `PATCH /debts/{debt_id}` 用。`None` のフィールドは送らない
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
+pub struct DebtPatchRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debt_amount: Option<Yen>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debt_fee: Option<Yen>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debt_delinquency_charge: Option<Yen>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repayment_due_at: Option<DateTime<FixedOffset>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "ordered_optional_map"
+    )]
+    pub custom_fields: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remind_segments: Option<Vec<String>>,
+}
+
+fn ordered_optional_map<S>(
+    value: &Option<HashMap<String, String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    value
+        .as_ref()
+        .map(|v| v.iter().collect::<BTreeMap<_, _>>())
+        .serialize(serializer)
+}
+
+/// `GET /debts` の絞り込み条件
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
+pub struct DebtFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debtor_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<DebtStatusVariable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repayment_due_from: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repayment_due_to: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partner_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remind_segment: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PartnerRequest {
     pub id: String,
@@ -76,9 +362,10 @@ pub struct Partner {
 
 #[cfg(test)]
 mod tests {
-    use crate::{fixture::lecto_debt_response, DebtStatusVariable};
+    use crate::fixture::{self, lecto_debt_response};
 
     use super::*;
+    use crate::tz::JST;
     use chrono::TimeZone;
     use pretty_assertions::assert_eq;
 
@@ -87,11 +374,11 @@ mod tests {
         let req = DebtRequest {
             debt_id: "1234-4321".into(),
             debtor_id: "5678".into(),
-            dealt_at: Local.with_ymd_and_hms(2021, 12, 1, 12, 13, 0).unwrap(),
-            debt_amount: 7400,
-            debt_fee: Some(540),
-            debt_delinquency_charge: Some(680),
-            repayment_due_at: Local.with_ymd_and_hms(2022, 3, 1, 0, 0, 0).unwrap(),
+            dealt_at: JST.with_ymd_and_hms(2021, 12, 1, 12, 13, 0).unwrap(),
+            debt_amount: Yen::new(7400),
+            debt_fee: Some(Yen::new(540)),
+            debt_delinquency_charge: Some(Yen::new(680)),
+            repayment_due_at: JST.with_ymd_and_hms(2022, 3, 1, 0, 0, 0).unwrap(),
             custom_fields: [
                 ("item_name".into(), "iPhoneSE 12".into()),
                 ("total_amount".into(), "17000".into()),
@@ -106,8 +393,8 @@ mod tests {
             debt_status: Some(DebtStatusRequest {
                 debt_id: "1234-5678".into(),
                 status: Some(DebtStatusVariable::Repaid),
-                changed_at: Local.with_ymd_and_hms(2021, 11, 15, 12, 34, 0).unwrap(),
-                expire_at: Local.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap(),
+                changed_at: JST.with_ymd_and_hms(2021, 11, 15, 12, 34, 0).unwrap(),
+                expire_at: JST.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap(),
                 status_id: None,
             }),
         };
@@ -121,6 +408,208 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_serialize_patch_request() -> anyhow::Result<()> {
+        let req = DebtPatchRequest {
+            debt_amount: Some(Yen::new(5000)),
+            repayment_due_at: Some(JST.with_ymd_and_hms(2022, 4, 1, 0, 0, 0).unwrap()),
+            custom_fields: Some(
+                [
+                    ("total_amount".into(), "15000".into()),
+                    ("elapsed_month".into(), "-1".into()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        };
+
+        let serialized = serde_json::to_string(&req)?;
+        assert_eq!(
+            serialized,
+            r#"{"debt_amount":5000,"repayment_due_at":"2022-04-01T00:00:00+09:00","custom_fields":{"elapsed_month":"-1","total_amount":"15000"}}"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(fixture::debt_request_sample_data().validate(), Ok(()));
+
+        let mut req = fixture::debt_request_sample_data();
+        req.debt_amount = Yen::new(-1);
+        req.debt_delinquency_charge = Some(Yen::new(-680));
+        req.repayment_due_at = JST.with_ymd_and_hms(2021, 11, 30, 0, 0, 0).unwrap();
+        req.remind_segments = Some(vec!["y2021".into(), " ".into()]);
+        if let Some(debt_status) = req.debt_status.as_mut() {
+            debt_status.debt_id = "1234-5678".into();
+        }
+
+        let errors = req.validate().unwrap_err();
+        assert_eq!(
+            errors
+                .errors
+                .iter()
+                .map(|e| (e.field.as_str(), e.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("debt_amount", FieldErrorKind::OutOfRange),
+                ("debt_delinquency_charge", FieldErrorKind::OutOfRange),
+                ("repayment_due_at", FieldErrorKind::OutOfRange),
+                ("debt_status.debt_id", FieldErrorKind::Mismatch),
+                ("remind_segments[1]", FieldErrorKind::Required),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_same_day_due() {
+        let req = DebtRequest {
+            repayment_due_at: JST.with_ymd_and_hms(2021, 12, 1, 0, 0, 0).unwrap(),
+            ..fixture::debt_request_sample_data()
+        };
+        assert_eq!(req.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_builder() {
+        let req = DebtRequest::builder("1234-4321", "5678")
+            .dealt_at(JST.with_ymd_and_hms(2021, 12, 1, 12, 13, 0).unwrap())
+            .debt_amount(7400)
+            .debt_fee(540)
+            .repayment_due_at(JST.with_ymd_and_hms(2022, 3, 1, 23, 59, 59).unwrap())
+            .custom_field("item_name", "Windowsノートパソコン")
+            .remind_segment("y2021")
+            .build()
+            .unwrap();
+
+        assert_eq!(req.debt_fee, Some(Yen::new(540)));
+        assert_eq!(req.debt_delinquency_charge, None);
+        assert_eq!(req.remind_segments, Some(vec!["y2021".to_string()]));
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let errors = DebtRequest::builder("1234-4321", "5678")
+            .debt_amount(-1)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            errors
+                .errors
+                .iter()
+                .map(|e| e.field.as_str())
+                .collect::<Vec<_>>(),
+            vec!["dealt_at", "repayment_due_at"]
+        );
+
+        let errors = DebtRequest::builder("1234-4321", "")
+            .dealt_at(JST.with_ymd_and_hms(2021, 12, 1, 12, 13, 0).unwrap())
+            .debt_amount(-1)
+            .repayment_due_at(JST.with_ymd_and_hms(2021, 11, 1, 0, 0, 0).unwrap())
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            errors
+                .errors
+                .iter()
+                .map(|e| e.field.as_str())
+                .collect::<Vec<_>>(),
+            vec!["debtor_id", "debt_amount", "repayment_due_at"]
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct ItemFields {
+        item_name: String,
+        total_amount: i64,
+    }
+
+    #[test]
+    fn test_custom_fields_as() -> anyhow::Result<()> {
+        let mut debt: Debt = serde_json::from_value(lecto_debt_response())?;
+        assert_eq!(
+            debt.custom_fields_as::<ItemFields>()?,
+            ItemFields {
+                item_name: "iPhoneSE 12".into(),
+                total_amount: 17000,
+            }
+        );
+
+        debt.custom_fields
+            .insert("total_amount".into(), "17000円".into());
+        let err = debt.custom_fields_as::<ItemFields>().unwrap_err();
+        assert_eq!(err.field, "total_amount");
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_custom_fields() -> anyhow::Result<()> {
+        let req = DebtRequest::builder("1234-4321", "5678")
+            .dealt_at(JST.with_ymd_and_hms(2021, 12, 1, 12, 13, 0).unwrap())
+            .debt_amount(7400)
+            .repayment_due_at(JST.with_ymd_and_hms(2022, 3, 1, 23, 59, 59).unwrap())
+            .custom_field("memo", "memo")
+            .custom_fields(&ItemFields {
+                item_name: "iPhoneSE 12".into(),
+                total_amount: 17000,
+            })
+            .build()?;
+        assert_eq!(
+            req.custom_fields.get("total_amount").map(String::as_str),
+            Some("17000")
+        );
+        assert_eq!(req.custom_fields.len(), 3);
+
+        let errors = DebtRequest::builder("1234-4321", "5678")
+            .dealt_at(JST.with_ymd_and_hms(2021, 12, 1, 12, 13, 0).unwrap())
+            .debt_amount(7400)
+            .repayment_due_at(JST.with_ymd_and_hms(2022, 3, 1, 23, 59, 59).unwrap())
+            .custom_fields(&BTreeMap::from([("items", vec!["a", "b"])]))
+            .build()
+            .unwrap_err();
+        assert_eq!(errors.field("custom_fields.items").count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_repayment_due_date() -> anyhow::Result<()> {
+        let mut debt: Debt = serde_json::from_value(lecto_debt_response())?;
+        // JSTの3/1 0:00はUTCでは2/28
+        debt.repayment_due_at = "2022-02-28T15:00:00Z".parse()?;
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let march_1 = NaiveDate::from_ymd_opt(2022, 3, 1).unwrap();
+
+        assert_eq!(debt.repayment_due_date(&JST), march_1);
+        assert_eq!(debt.repayment_due_date(&utc), march_1.pred_opt().unwrap());
+        assert!(!debt.is_overdue(march_1, &JST));
+        assert!(debt.is_overdue(march_1, &utc));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_compares_dates_in_dealt_at_offset() {
+        let mut req = fixture::debt_request_sample_data();
+        req.dealt_at = JST.with_ymd_and_hms(2021, 12, 1, 8, 0, 0).unwrap();
+        // UTCでは11/30だがJSTでは12/1
+        req.repayment_due_at = "2021-11-30T23:00:00Z".parse().unwrap();
+        assert_eq!(req.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_total_outstanding() -> anyhow::Result<()> {
+        let mut debt: Debt = serde_json::from_value(lecto_debt_response())?;
+        debt.debt_amount = Yen::new(10000);
+        debt.debt_fee = Some(Yen::new(100));
+        debt.debt_delinquency_charge = None;
+        assert_eq!(debt.total_outstanding(), Some(Yen::new(10100)));
+
+        debt.debt_fee = Some(Yen::new(i64::MAX));
+        assert_eq!(debt.total_outstanding(), None);
+        Ok(())
+    }
+
     #[test]
     fn test_deseialize_response() -> anyhow::Result<()> {
         let res_json = serde_json::to_string(&lecto_debt_response())?;
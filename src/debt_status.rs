@@ -1,6 +1,12 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, TimeZone};
 use serde::{Deserialize, Serialize};
 
+use crate::tz::JST;
+
+pub mod timeline;
+
+pub use timeline::{StatusTimeline, TimelineIssue};
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum DebtStatusVariable {
@@ -10,33 +16,100 @@ pub enum DebtStatusVariable {
     DebtCancelled,
     BadDebtFixed,
     Suspended,
+    /// このクライアントが知らないステータス。受け取った文字列をそのまま持ち、そのまま送り返す
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl DebtStatusVariable {
+    /// このステータスから遷移できるステータス
+    pub fn allowed_transitions(&self) -> &'static [DebtStatusVariable] {
+        use DebtStatusVariable::*;
+        match self {
+            Active | AutoActivated => &[Repaid, Suspended, DebtCancelled, BadDebtFixed],
+            Suspended => &[Active, Repaid, DebtCancelled, BadDebtFixed],
+            BadDebtFixed => &[Repaid],
+            Repaid | DebtCancelled | Unknown(_) => &[],
+        }
+    }
+
+    pub fn can_transition_to(&self, to: &DebtStatusVariable) -> bool {
+        self.allowed_transitions().contains(to)
+    }
+
+    /// 完済・債権取消はこれ以上遷移しない
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            DebtStatusVariable::Repaid | DebtStatusVariable::DebtCancelled
+        )
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, DebtStatusVariable::Unknown(_))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct DebtStatus {
     pub id: i64,
     pub debt_id: String,
-    pub changed_at: DateTime<Local>,
-    pub expire_at: DateTime<Local>,
+    pub changed_at: DateTime<FixedOffset>,
+    pub expire_at: DateTime<FixedOffset>,
     pub status: DebtStatusVariable,
     pub status_id: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+impl DebtStatus {
+    pub fn can_transition_to(&self, to: &DebtStatusVariable) -> bool {
+        self.status.can_transition_to(to)
+    }
+
+    /// `expire_at` が9999年 (期限なし) か
+    pub fn is_open_ended(&self) -> bool {
+        timeline::is_open_ended(&self.expire_at)
+    }
+}
+
+/// 期限を設けないときの `expire_at` (9999-12-31T23:59:59+09:00)
+pub fn default_expire_at() -> DateTime<FixedOffset> {
+    JST.with_ymd_and_hms(9999, 12, 31, 23, 59, 59)
+        .single()
+        .unwrap_or(DateTime::<FixedOffset>::MAX_UTC.fixed_offset())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DebtStatusRequest {
     pub debt_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status: Option<DebtStatusVariable>,
-    pub changed_at: DateTime<Local>,
-    pub expire_at: DateTime<Local>,
+    pub changed_at: DateTime<FixedOffset>,
+    pub expire_at: DateTime<FixedOffset>,
+}
+
+impl DebtStatusRequest {
+    /// `changed_at` から期限なしで `status` にするリクエスト
+    pub fn new(
+        debt_id: impl Into<String>,
+        status: DebtStatusVariable,
+        changed_at: impl Into<DateTime<FixedOffset>>,
+    ) -> Self {
+        Self {
+            debt_id: debt_id.into(),
+            status_id: None,
+            status: Some(status),
+            changed_at: changed_at.into(),
+            expire_at: default_expire_at(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::TimeZone;
     use pretty_assertions::assert_eq;
+    use rstest::rstest;
 
     use crate::fixture::lecto_debt_status_response;
 
@@ -47,8 +120,8 @@ mod tests {
         let req = DebtStatusRequest {
             debt_id: "1234-5678".into(),
             status: Some(DebtStatusVariable::Repaid),
-            changed_at: Local.with_ymd_and_hms(2021, 11, 15, 12, 34, 0).unwrap(),
-            expire_at: Local.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap(),
+            changed_at: JST.with_ymd_and_hms(2021, 11, 15, 12, 34, 0).unwrap(),
+            expire_at: JST.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap(),
             status_id: Some("LECTO-001".into()),
         };
 
@@ -61,6 +134,71 @@ mod tests {
         Ok(())
     }
 
+    #[rstest]
+    #[case(DebtStatusVariable::Active, DebtStatusVariable::Repaid, true)]
+    #[case(DebtStatusVariable::Active, DebtStatusVariable::Suspended, true)]
+    #[case(DebtStatusVariable::Suspended, DebtStatusVariable::Active, true)]
+    #[case(
+        DebtStatusVariable::AutoActivated,
+        DebtStatusVariable::BadDebtFixed,
+        true
+    )]
+    #[case(DebtStatusVariable::BadDebtFixed, DebtStatusVariable::Repaid, true)]
+    #[case(DebtStatusVariable::BadDebtFixed, DebtStatusVariable::Active, false)]
+    #[case(DebtStatusVariable::Active, DebtStatusVariable::Active, false)]
+    #[case(DebtStatusVariable::Active, DebtStatusVariable::AutoActivated, false)]
+    #[case(DebtStatusVariable::Repaid, DebtStatusVariable::Active, false)]
+    #[case(DebtStatusVariable::DebtCancelled, DebtStatusVariable::Repaid, false)]
+    fn test_can_transition_to(
+        #[case] from: DebtStatusVariable,
+        #[case] to: DebtStatusVariable,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(from.can_transition_to(&to), expected);
+    }
+
+    #[test]
+    fn test_is_terminal() {
+        assert!(DebtStatusVariable::Repaid.is_terminal());
+        assert!(DebtStatusVariable::DebtCancelled.is_terminal());
+        assert!(!DebtStatusVariable::Suspended.is_terminal());
+        assert!(!DebtStatusVariable::Unknown("in_negotiation".into()).is_terminal());
+    }
+
+    #[rstest]
+    #[case(r#""bad_debt_fixed""#, DebtStatusVariable::BadDebtFixed)]
+    #[case(r#""in_negotiation""#, DebtStatusVariable::Unknown("in_negotiation".into()))]
+    fn test_serde_round_trip(
+        #[case] json: &str,
+        #[case] expected: DebtStatusVariable,
+    ) -> anyhow::Result<()> {
+        let status: DebtStatusVariable = serde_json::from_str(json)?;
+        assert_eq!(status, expected);
+        assert_eq!(serde_json::to_string(&status)?, json);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_has_no_transitions() {
+        let unknown = DebtStatusVariable::Unknown("in_negotiation".into());
+        assert!(!unknown.can_transition_to(&DebtStatusVariable::Repaid));
+        assert!(!DebtStatusVariable::Active.can_transition_to(&unknown));
+    }
+
+    #[test]
+    fn test_new_request() -> anyhow::Result<()> {
+        let req = DebtStatusRequest::new(
+            "1234-5678",
+            DebtStatusVariable::Repaid,
+            JST.with_ymd_and_hms(2021, 11, 15, 12, 34, 0).unwrap(),
+        );
+        assert_eq!(
+            serde_json::to_string(&req)?,
+            r#"{"debt_id":"1234-5678","status":"repaid","changed_at":"2021-11-15T12:34:00+09:00","expire_at":"9999-12-31T23:59:59+09:00"}"#
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_deserialize_response() -> anyhow::Result<()> {
         let res_json = serde_json::to_string(&lecto_debt_status_response())?;
@@ -0,0 +1,210 @@
+use chrono::{DateTime, Datelike, FixedOffset, Utc};
+
+use crate::debt_status::DebtStatus;
+
+/// この年以降の `expire_at` は期限なし (9999-12-31T23:59:59) とみなす
+const OPEN_ENDED_YEAR: i32 = 9999;
+
+pub fn is_open_ended(expire_at: &DateTime<FixedOffset>) -> bool {
+    expire_at.year() >= OPEN_ENDED_YEAR
+}
+
+/// 期間の終わり。期限なしなら `None`
+fn period_end(status: &DebtStatus) -> Option<DateTime<FixedOffset>> {
+    (!is_open_ended(&status.expire_at)).then_some(status.expire_at)
+}
+
+/// 履歴の期間の重なり・抜け
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineIssue {
+    /// `earlier_id` の期間が終わる前に `later_id` が始まっている
+    Overlap {
+        earlier_id: i64,
+        later_id: i64,
+        from: DateTime<FixedOffset>,
+        /// 重なりの終わり。両方とも期限なしなら `None`
+        to: Option<DateTime<FixedOffset>>,
+    },
+    /// `earlier_id` の期間が終わってから `later_id` が始まるまでステータスがない
+    Gap {
+        earlier_id: i64,
+        later_id: i64,
+        from: DateTime<FixedOffset>,
+        to: DateTime<FixedOffset>,
+    },
+}
+
+/// 1債権のステータス履歴。`changed_at` から `expire_at` の直前までを有効期間とする
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StatusTimeline {
+    statuses: Vec<DebtStatus>,
+}
+
+impl StatusTimeline {
+    pub fn new(statuses: impl IntoIterator<Item = DebtStatus>) -> Self {
+        let mut statuses: Vec<DebtStatus> = statuses.into_iter().collect();
+        statuses.sort_by(|a, b| a.changed_at.cmp(&b.changed_at).then(a.id.cmp(&b.id)));
+        Self { statuses }
+    }
+
+    /// `changed_at` の昇順
+    pub fn statuses(&self) -> &[DebtStatus] {
+        &self.statuses
+    }
+
+    /// `at` 時点で有効なステータス。重なっている場合は後から始まったもの。
+    /// オフセットが違っても同じ時刻として比べる
+    pub fn status_at(&self, at: impl Into<DateTime<FixedOffset>>) -> Option<&DebtStatus> {
+        let at = at.into();
+        self.statuses
+            .iter()
+            .rev()
+            .find(|s| s.changed_at <= at && period_end(s).map_or(true, |end| at < end))
+    }
+
+    pub fn current(&self) -> Option<&DebtStatus> {
+        self.status_at(Utc::now())
+    }
+
+    /// 期間の重なりと抜けを `changed_at` の順に返す
+    pub fn issues(&self) -> Vec<TimelineIssue> {
+        let mut issues = vec![];
+        // これまでで最も遅く終わる期間 (期限なしは `None` で最大)
+        let mut latest: Option<&DebtStatus> = None;
+
+        for status in &self.statuses {
+            if let Some(prev) = latest {
+                let prev_end = period_end(prev);
+                match prev_end {
+                    Some(end) if end < status.changed_at => issues.push(TimelineIssue::Gap {
+                        earlier_id: prev.id,
+                        later_id: status.id,
+                        from: end,
+                        to: status.changed_at,
+                    }),
+                    Some(end) if end == status.changed_at => {}
+                    _ => issues.push(TimelineIssue::Overlap {
+                        earlier_id: prev.id,
+                        later_id: status.id,
+                        from: status.changed_at,
+                        to: match (prev_end, period_end(status)) {
+                            (Some(a), Some(b)) => Some(a.min(b)),
+                            (a, b) => a.or(b),
+                        },
+                    }),
+                }
+                let ends_later = match (prev_end, period_end(status)) {
+                    (None, _) => false,
+                    (Some(_), None) => true,
+                    (Some(a), Some(b)) => b > a,
+                };
+                if ends_later {
+                    latest = Some(status);
+                }
+            } else {
+                latest = Some(status);
+            }
+        }
+        issues
+    }
+
+    /// 重なりも抜けもない
+    pub fn is_consistent(&self) -> bool {
+        self.issues().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tz::JST;
+    use crate::DebtStatusVariable;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    fn at(month: u32, day: u32) -> DateTime<FixedOffset> {
+        JST.with_ymd_and_hms(2022, month, day, 0, 0, 0).unwrap()
+    }
+
+    fn status(
+        id: i64,
+        status: DebtStatusVariable,
+        changed_at: DateTime<FixedOffset>,
+        expire_at: Option<DateTime<FixedOffset>>,
+    ) -> DebtStatus {
+        DebtStatus {
+            id,
+            debt_id: "debt id".into(),
+            changed_at,
+            expire_at: expire_at.unwrap_or_else(crate::debt_status::default_expire_at),
+            status,
+            status_id: format!("LECTO-{}", id),
+        }
+    }
+
+    #[test]
+    fn test_status_at() {
+        let timeline = StatusTimeline::new([
+            status(2, DebtStatusVariable::Suspended, at(2, 1), Some(at(3, 1))),
+            status(3, DebtStatusVariable::Repaid, at(3, 1), None),
+            status(1, DebtStatusVariable::Active, at(1, 1), Some(at(2, 1))),
+        ]);
+
+        let status_at = |t| timeline.status_at(t).map(|s| s.id);
+        assert_eq!(status_at(at(1, 1) - chrono::Duration::seconds(1)), None);
+        assert_eq!(status_at(at(1, 15)), Some(1));
+        assert_eq!(status_at(at(2, 1)), Some(2));
+        assert_eq!(status_at(at(12, 31)), Some(3));
+        assert_eq!(timeline.current().map(|s| s.id), Some(3));
+        assert!(timeline.is_consistent());
+    }
+
+    #[test]
+    fn test_issues() {
+        let timeline = StatusTimeline::new([
+            status(1, DebtStatusVariable::Active, at(1, 1), None),
+            status(2, DebtStatusVariable::Suspended, at(2, 1), Some(at(3, 1))),
+            status(3, DebtStatusVariable::Active, at(4, 1), Some(at(5, 1))),
+        ]);
+
+        assert_eq!(
+            timeline.issues(),
+            vec![
+                TimelineIssue::Overlap {
+                    earlier_id: 1,
+                    later_id: 2,
+                    from: at(2, 1),
+                    to: Some(at(3, 1)),
+                },
+                TimelineIssue::Overlap {
+                    earlier_id: 1,
+                    later_id: 3,
+                    from: at(4, 1),
+                    to: Some(at(5, 1)),
+                },
+            ]
+        );
+        // 重なっている間は後から始まったものを返す
+        assert_eq!(timeline.status_at(at(2, 15)).map(|s| s.id), Some(2));
+        assert_eq!(timeline.status_at(at(3, 15)).map(|s| s.id), Some(1));
+    }
+
+    #[test]
+    fn test_gap() {
+        let timeline = StatusTimeline::new([
+            status(1, DebtStatusVariable::Active, at(1, 1), Some(at(2, 1))),
+            status(2, DebtStatusVariable::Repaid, at(3, 1), None),
+        ]);
+
+        assert_eq!(
+            timeline.issues(),
+            vec![TimelineIssue::Gap {
+                earlier_id: 1,
+                later_id: 2,
+                from: at(2, 1),
+                to: at(3, 1),
+            }]
+        );
+        assert_eq!(timeline.status_at(at(2, 15)), None);
+    }
+}
@@ -1,9 +1,12 @@
-use chrono::NaiveDate;
-use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::convert::From;
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Eq, Hash)]
+use crate::normalize::{self, Normalized};
+use crate::tz::{self, JST};
+use crate::validation::{self, FieldErrorKind, ValidationErrors};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub struct Debtor {
     pub id: u64,
     pub debtor_id: String,
@@ -13,7 +16,7 @@ pub struct Debtor {
     pub phone_number: DebtorPhoneNumber,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub struct DebtorBasicInformation {
     pub name: String,
     pub name_kana: Option<String>,
@@ -21,25 +24,26 @@ pub struct DebtorBasicInformation {
     pub gender: Gender,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub struct DebtorEmail {
     pub email: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub struct DebtorAddress {
     pub address: String,
-    pub kyc_done: bool,
+    /// 未知の値もそのまま持つ。本人確認済みかは `KycDone::is_done` で判断する
+    pub kyc_done: KycDone,
     pub postal_code: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub struct DebtorPhoneNumber {
     pub phone_number: Option<String>,
     pub mobile_number: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct DebtorRequest {
     pub debtor_id: String,
     pub name: String,
@@ -55,6 +59,111 @@ pub struct DebtorRequest {
     pub mobile_number: String,
 }
 
+impl DebtorRequest {
+    /// 送信前に各項目の形式を確認する。
+    /// 電話番号・携帯番号・カナ・郵便番号は空なら確認しない。
+    /// 生年月日は日本時間の今日までを許す。`Client` は業務タイムゾーンの今日で `validate_at` を呼ぶ
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        self.validate_at(tz::today(&JST))
+    }
+
+    /// `validate` と同じだが、生年月日の上限を `today` にする
+    pub fn validate_at(&self, today: NaiveDate) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        for (field, value) in [
+            ("debtor_id", &self.debtor_id),
+            ("name", &self.name),
+            ("email", &self.email),
+            ("address", &self.address),
+        ] {
+            if validation::is_blank(value) {
+                errors.push(field, FieldErrorKind::Required, "is required");
+            }
+        }
+
+        if !validation::is_blank(&self.email) && !validation::is_email(&self.email) {
+            errors.push(
+                "email",
+                FieldErrorKind::InvalidFormat,
+                "is not a valid email",
+            );
+        }
+        if !self.postal_code.is_empty() && !validation::is_postal_code(&self.postal_code) {
+            errors.push(
+                "postal_code",
+                FieldErrorKind::InvalidFormat,
+                "must be 7 digits (e.g. 1234567 or 123-4567)",
+            );
+        }
+        if !self.phone_number.is_empty() && !validation::is_phone_number(&self.phone_number) {
+            errors.push(
+                "phone_number",
+                FieldErrorKind::InvalidFormat,
+                "is not a valid Japanese phone number",
+            );
+        }
+        if !self.mobile_number.is_empty() && !validation::is_mobile_number(&self.mobile_number) {
+            errors.push(
+                "mobile_number",
+                FieldErrorKind::InvalidFormat,
+                "is not a valid Japanese mobile number",
+            );
+        }
+        if !self.name_kana.is_empty() && !validation::is_katakana(&self.name_kana) {
+            errors.push(
+                "name_kana",
+                FieldErrorKind::InvalidFormat,
+                "must be full-width katakana",
+            );
+        }
+        if let Some(birth_date) = self.birth_date {
+            if !validation::is_plausible_birth_date(birth_date, today) {
+                errors.push(
+                    "birth_date",
+                    FieldErrorKind::OutOfRange,
+                    "must be between 1900-01-01 and today",
+                );
+            }
+        }
+
+        errors.into_result()
+    }
+
+    /// Lectoが期待する形に揃える。
+    /// 郵便番号・電話番号は半角数字のみ、カナは全角カタカナ、全項目の前後の空白を除く
+    pub fn normalize(mut self) -> Normalized<Self> {
+        let mut changes = vec![];
+        for (field, value) in [
+            ("debtor_id", &mut self.debtor_id),
+            ("name", &mut self.name),
+            ("address", &mut self.address),
+        ] {
+            normalize::apply(&mut changes, field, value, normalize::trim);
+        }
+        normalize::apply(&mut changes, "email", &mut self.email, |v| {
+            normalize::to_half_width_ascii(v.trim())
+        });
+        normalize::apply(
+            &mut changes,
+            "name_kana",
+            &mut self.name_kana,
+            normalize::to_full_width_katakana,
+        );
+        for (field, value) in [
+            ("postal_code", &mut self.postal_code),
+            ("phone_number", &mut self.phone_number),
+            ("mobile_number", &mut self.mobile_number),
+        ] {
+            normalize::apply(&mut changes, field, value, normalize::to_digits);
+        }
+
+        Normalized {
+            value: self,
+            changes,
+        }
+    }
+}
+
 // kyc_doneがintegerかboolかの違い
 // 内部で使うための物で、外部には公開しない
 #[derive(Debug, Clone, PartialEq, Serialize, Default)]
@@ -82,11 +191,73 @@ impl From<DebtorRequest> for DebtorRawRequest {
             gender: item.gender,
             email: item.email,
             address: item.address,
-            kyc_done: if item.kyc_done {
-                KycDone::Done
-            } else {
-                KycDone::NotDone
-            },
+            kyc_done: KycDone::from(item.kyc_done),
+            postal_code: item.postal_code,
+            phone_number: item.phone_number,
+            mobile_number: item.mobile_number,
+        }
+    }
+}
+
+/// `PATCH /debtors/{debtor_id}` 用。`None` のフィールドは送らない
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
+pub struct DebtorPatchRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_kana: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub birth_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<Gender>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kyc_done: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postal_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mobile_number: Option<String>,
+}
+
+// DebtorRawRequestと同じくkyc_doneをintegerにしたもの
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
+pub struct DebtorRawPatchRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_kana: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub birth_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<Gender>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kyc_done: Option<KycDone>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postal_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mobile_number: Option<String>,
+}
+impl From<DebtorPatchRequest> for DebtorRawPatchRequest {
+    fn from(item: DebtorPatchRequest) -> Self {
+        Self {
+            name: item.name,
+            name_kana: item.name_kana,
+            birth_date: item.birth_date,
+            gender: item.gender,
+            email: item.email,
+            address: item.address,
+            kyc_done: item.kyc_done.map(KycDone::from),
             postal_code: item.postal_code,
             phone_number: item.phone_number,
             mobile_number: item.mobile_number,
@@ -104,7 +275,7 @@ impl From<DebtorAddressResponse> for DebtorAddress {
     fn from(item: DebtorAddressResponse) -> Self {
         Self {
             address: item.address,
-            kyc_done: item.kyc_done == KycDone::Done,
+            kyc_done: item.kyc_done,
             postal_code: item.postal_code,
         }
     }
@@ -134,36 +305,120 @@ impl From<DebtorResponse> for Debtor {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// `GET /debtors` の絞り込み条件
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
+pub struct DebtorFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_since: Option<DateTime<FixedOffset>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_kyc_done"
+    )]
+    pub kyc_done: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+fn serialize_kyc_done<S>(value: &Option<bool>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    value.map(KycDone::from).serialize(serializer)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum Gender {
+    #[default]
     None,
     Male,
     Female,
     Other,
+    /// このクライアントが知らない性別。受け取った文字列をそのまま持つ
+    #[serde(untagged)]
+    Unknown(String),
 }
 
-impl Default for Gender {
-    fn default() -> Self {
-        Self::None
+/// JSONでは `1` / `0` の整数
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum KycDone {
+    Done,
+
+    #[default]
+    NotDone,
+    /// `0` / `1` 以外の値。受け取った値をそのまま持つ
+    Unknown(u8),
+}
+
+impl KycDone {
+    /// 本人確認済みか。未知の値は済みとはみなさない
+    pub fn is_done(&self) -> bool {
+        *self == Self::Done
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize_repr, Serialize_repr, Eq, Hash, Default)]
-#[repr(u8)]
-pub enum KycDone {
-    Done = 1,
+impl From<u8> for KycDone {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Self::Done,
+            0 => Self::NotDone,
+            v => Self::Unknown(v),
+        }
+    }
+}
 
-    #[default]
-    NotDone = 0,
+impl From<&KycDone> for u8 {
+    fn from(value: &KycDone) -> Self {
+        match value {
+            KycDone::Done => 1,
+            KycDone::NotDone => 0,
+            KycDone::Unknown(v) => *v,
+        }
+    }
+}
+
+impl Serialize for KycDone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.into())
+    }
+}
+
+/// 以前の `Debtor` のJSON (`kyc_done` が真偽値) も読めるようにする
+impl<'de> Deserialize<'de> for KycDone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Int(u8),
+            Bool(bool),
+        }
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Int(v) => Self::from(v),
+            Repr::Bool(v) => Self::from(v),
+        })
+    }
+}
+
+impl From<bool> for KycDone {
+    fn from(item: bool) -> Self {
+        if item {
+            Self::Done
+        } else {
+            Self::NotDone
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::fixture::lecto_debtor_response;
+    use crate::fixture::{self, lecto_debtor_response};
 
     use super::*;
+    use assert_matches::assert_matches;
     use pretty_assertions::assert_eq;
+    use rstest::rstest;
 
     #[test]
     fn test_serialize_raw_request() -> anyhow::Result<()> {
@@ -190,10 +445,184 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_serialize_raw_patch_request() -> anyhow::Result<()> {
+        let req = DebtorRawPatchRequest::from(DebtorPatchRequest {
+            email: Some("new@example.com".into()),
+            kyc_done: Some(true),
+            mobile_number: Some("08012345678".into()),
+            ..Default::default()
+        });
+
+        let serialized = serde_json::to_string(&req)?;
+        assert_eq!(
+            serialized,
+            r#"{"email":"new@example.com","kyc_done":1,"mobile_number":"08012345678"}"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_filter() -> anyhow::Result<()> {
+        let filter = DebtorFilter {
+            kyc_done: Some(false),
+            name: Some("名前".into()),
+            ..Default::default()
+        };
+
+        let serialized = serde_json::to_string(&filter)?;
+        assert_eq!(serialized, r#"{"kyc_done":0,"name":"名前"}"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(fixture::debtor_request_sample_data().validate(), Ok(()));
+
+        let req = DebtorRequest {
+            name: " ".into(),
+            email: "sample@example".into(),
+            postal_code: "333-666".into(),
+            phone_number: "031234567".into(),
+            mobile_number: "0312345678".into(),
+            name_kana: "かな".into(),
+            birth_date: NaiveDate::from_ymd_opt(1800, 1, 1),
+            ..fixture::debtor_request_sample_data()
+        };
+        let errors = req.validate().unwrap_err();
+
+        let fields: Vec<(&str, FieldErrorKind)> = errors
+            .errors
+            .iter()
+            .map(|e| (e.field.as_str(), e.kind))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("name", FieldErrorKind::Required),
+                ("email", FieldErrorKind::InvalidFormat),
+                ("postal_code", FieldErrorKind::InvalidFormat),
+                ("phone_number", FieldErrorKind::InvalidFormat),
+                ("mobile_number", FieldErrorKind::InvalidFormat),
+                ("name_kana", FieldErrorKind::InvalidFormat),
+                ("birth_date", FieldErrorKind::OutOfRange),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_at() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let req = DebtorRequest {
+            birth_date: Some(today),
+            ..fixture::debtor_request_sample_data()
+        };
+        assert_eq!(req.validate_at(today), Ok(()));
+        assert_matches!(req.validate_at(today.pred_opt().unwrap()), Err(errors) => {
+            assert_eq!(errors.errors[0].field, "birth_date");
+        });
+    }
+
+    #[test]
+    fn test_validate_skips_empty_optional_fields() {
+        let req = DebtorRequest {
+            name_kana: String::new(),
+            postal_code: String::new(),
+            phone_number: String::new(),
+            mobile_number: String::new(),
+            birth_date: None,
+            ..fixture::debtor_request_sample_data()
+        };
+        assert_eq!(req.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let normalized = DebtorRequest {
+            name: " 名前　".into(),
+            name_kana: "ｶﾞｸ ﾀﾛｳ".into(),
+            email: "ｓａｍｐｌｅ@example.com ".into(),
+            postal_code: "３３３－６６６６".into(),
+            phone_number: "(03)1234-5678".into(),
+            ..fixture::debtor_request_sample_data()
+        }
+        .normalize();
+
+        assert_eq!(
+            normalized.value,
+            DebtorRequest {
+                name_kana: "ガク タロウ".into(),
+                ..fixture::debtor_request_sample_data()
+            }
+        );
+        assert_eq!(
+            normalized
+                .changes
+                .iter()
+                .map(|c| (c.field.as_str(), c.before.as_str(), c.after.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("name", " 名前　", "名前"),
+                ("email", "ｓａｍｐｌｅ@example.com ", "sample@example.com"),
+                ("name_kana", "ｶﾞｸ ﾀﾛｳ", "ガク タロウ"),
+                ("postal_code", "３３３－６６６６", "3336666"),
+                ("phone_number", "(03)1234-5678", "0312345678"),
+            ]
+        );
+        assert!(normalized.value.validate().is_ok());
+    }
+
+    #[test]
+    fn test_normalize_unchanged() {
+        let normalized = fixture::debtor_request_sample_data().normalize();
+        assert!(!normalized.is_changed());
+    }
+
     #[test]
     fn test_deserialize_response() -> anyhow::Result<()> {
         let res_json = serde_json::to_string(&lecto_debtor_response())?;
         let _debtor: DebtorResponse = serde_json::from_str(&res_json)?;
         Ok(())
     }
+
+    #[rstest]
+    #[case(r#""female""#, Gender::Female)]
+    #[case(r#""non_binary""#, Gender::Unknown("non_binary".into()))]
+    fn test_gender_round_trip(#[case] json: &str, #[case] expected: Gender) -> anyhow::Result<()> {
+        let gender: Gender = serde_json::from_str(json)?;
+        assert_eq!(gender, expected);
+        assert_eq!(serde_json::to_string(&gender)?, json);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("1", KycDone::Done)]
+    #[case("0", KycDone::NotDone)]
+    #[case("2", KycDone::Unknown(2))]
+    fn test_kyc_done_round_trip(
+        #[case] json: &str,
+        #[case] expected: KycDone,
+    ) -> anyhow::Result<()> {
+        let kyc_done: KycDone = serde_json::from_str(json)?;
+        assert_eq!(kyc_done, expected);
+        assert_eq!(serde_json::to_string(&kyc_done)?, json);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_kyc_done_is_kept() -> anyhow::Result<()> {
+        let mut json = lecto_debtor_response();
+        json["address"]["kyc_done"] = 2.into();
+        let debtor = Debtor::from(serde_json::from_value::<DebtorResponse>(json)?);
+        assert_eq!(debtor.address.kyc_done, KycDone::Unknown(2));
+        assert!(!debtor.address.kyc_done.is_done());
+
+        let json = serde_json::to_value(&debtor)?;
+        assert_eq!(json["address"]["kyc_done"], 2);
+        assert_eq!(serde_json::from_value::<Debtor>(json)?, debtor);
+        assert_eq!(serde_json::from_str::<KycDone>("true")?, KycDone::Done);
+        Ok(())
+    }
 }
@@ -0,0 +1,469 @@
+//! `Remind` からメール・SMS・郵送用の文面を作って送る。
+//!
+//! リマインド (`label`) ごと・チャネルごとに1通を作る。債権は `Active` / `AutoActivated` のものだけを
+//! 文面に含め、それ以外は `SkipReason::InactiveDebt` としてレポートに残す。
+//! `with_dry_run(true)` なら送らずに、送る予定の文面を `DispatchReport` に集める
+use std::fmt;
+
+use chrono::FixedOffset;
+use serde::Serialize;
+
+use crate::debt::Debt;
+use crate::debt_status::DebtStatusVariable;
+use crate::debtor::Debtor;
+use crate::remind_group::query::merge_by_label;
+use crate::remind_group::remind::Remind;
+use crate::tz::JST;
+use crate::validation;
+
+pub mod channel;
+pub mod template;
+
+pub use channel::{
+    Channel, EmailChannel, FileChannel, PostalLetterChannel, SmsChannel, StdoutChannel,
+};
+pub use template::{Rendered, SimpleTemplate, TemplateContext, TemplateEngine};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    Email,
+    Sms,
+    PostalLetter,
+}
+
+impl ChannelKind {
+    /// 債務者の宛先。メールアドレスや携帯番号がない・形式が正しくないなら `None`
+    pub fn recipient(&self, debtor: &Debtor) -> Option<String> {
+        match self {
+            Self::Email => {
+                let email = debtor.email.email.trim();
+                validation::is_email(email).then(|| email.to_string())
+            }
+            Self::Sms => debtor
+                .phone_number
+                .mobile_number
+                .as_deref()
+                .map(str::trim)
+                .filter(|n| validation::is_mobile_number(n))
+                .map(str::to_string),
+            Self::PostalLetter => {
+                let address = debtor.address.address.trim();
+                if validation::is_blank(address) {
+                    return None;
+                }
+                Some(match debtor.address.postal_code.as_deref() {
+                    Some(code) if !validation::is_blank(code) => format!("〒{} {}", code, address),
+                    _ => address.to_string(),
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for ChannelKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Email => "email",
+            Self::Sms => "sms",
+            Self::PostalLetter => "postal_letter",
+        })
+    }
+}
+
+/// 送る文面1通分
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Message {
+    pub label: String,
+    pub kind: ChannelKind,
+    pub debtor_id: String,
+    /// 債務者名
+    pub name: String,
+    /// メールアドレス・携帯番号・住所のいずれか
+    pub to: String,
+    pub subject: Option<String>,
+    pub body: String,
+    /// 文面に含めた債権
+    pub debt_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DispatchError {
+    #[error("template error: {0}")]
+    Template(String),
+    /// チャネルが受け付けない文面 (件名がない、長すぎるなど)
+    #[error("rejected by {kind}: {message}")]
+    Rejected { kind: ChannelKind, message: String },
+    #[error("failed to send: {0}")]
+    Send(String),
+}
+
+impl From<std::io::Error> for DispatchError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Send(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// 文面に含めなかった債権
+    InactiveDebt {
+        debt_id: String,
+        status: DebtStatusVariable,
+    },
+    /// 有効な債権が1件もないリマインド
+    NoActiveDebts,
+    /// このチャネルの宛先がない
+    NoRecipient,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DispatchOutcome {
+    Sent(Message),
+    /// dry runで送らなかった
+    Planned(Message),
+    Skipped(SkipReason),
+    Failed(DispatchError),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DispatchEntry {
+    pub label: String,
+    pub debtor_id: String,
+    /// リマインド全体・債権単位の結果なら `None`
+    pub channel: Option<ChannelKind>,
+    pub outcome: DispatchOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DispatchReport {
+    pub dry_run: bool,
+    pub entries: Vec<DispatchEntry>,
+}
+
+impl DispatchReport {
+    /// 送った (dry runなら送る予定の) 文面
+    pub fn messages(&self) -> impl Iterator<Item = &Message> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.outcome {
+                DispatchOutcome::Sent(m) | DispatchOutcome::Planned(m) => Some(m),
+                _ => None,
+            })
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = (&DispatchEntry, &SkipReason)> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.outcome {
+                DispatchOutcome::Skipped(reason) => Some((entry, reason)),
+                _ => None,
+            })
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = (&DispatchEntry, &DispatchError)> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.outcome {
+                DispatchOutcome::Failed(e) => Some((entry, e)),
+                _ => None,
+            })
+    }
+
+    pub fn is_all_success(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
+
+/// 1行目に件数、以降に1件ずつ
+impl fmt::Display for DispatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}{} {}, {} skipped, {} failed",
+            if self.dry_run { "[dry run] " } else { "" },
+            self.messages().count(),
+            if self.dry_run { "planned" } else { "sent" },
+            self.skipped().count(),
+            self.failed().count(),
+        )?;
+        for entry in &self.entries {
+            let channel = entry.channel.map_or("-".to_string(), |c| c.to_string());
+            write!(f, "{}\t{}\t", entry.label, channel)?;
+            match &entry.outcome {
+                DispatchOutcome::Sent(m) => writeln!(f, "sent\t{}", m.to)?,
+                DispatchOutcome::Planned(m) => writeln!(f, "planned\t{}", m.to)?,
+                DispatchOutcome::Skipped(SkipReason::InactiveDebt { debt_id, status }) => {
+                    writeln!(f, "skipped\t{} is {:?}", debt_id, status)?
+                }
+                DispatchOutcome::Skipped(SkipReason::NoActiveDebts) => {
+                    writeln!(f, "skipped\tno active debts")?
+                }
+                DispatchOutcome::Skipped(SkipReason::NoRecipient) => {
+                    writeln!(f, "skipped\tno recipient")?
+                }
+                DispatchOutcome::Failed(e) => writeln!(f, "failed\t{}", e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// リマインドを文面にしてチャネルに流す
+pub struct Dispatcher {
+    engine: Box<dyn TemplateEngine>,
+    channels: Vec<Box<dyn Channel>>,
+    dry_run: bool,
+    business_time_zone: FixedOffset,
+}
+
+impl Dispatcher {
+    pub fn new(engine: impl TemplateEngine + 'static) -> Self {
+        Self {
+            engine: Box::new(engine),
+            channels: vec![],
+            dry_run: false,
+            business_time_zone: JST,
+        }
+    }
+
+    /// 送り先のチャネル。追加した順に送る
+    pub fn with_channel(mut self, channel: impl Channel + 'static) -> Self {
+        self.channels.push(Box::new(channel));
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// 文面の支払期日をどのタイムゾーンの日付にするか。デフォルトはJST
+    pub fn with_business_time_zone(mut self, tz: FixedOffset) -> Self {
+        self.business_time_zone = tz;
+        self
+    }
+
+    /// 同じ `label` のリマインドはまとめてから送る。失敗しても残りを続ける
+    pub async fn dispatch(&self, reminds: impl IntoIterator<Item = Remind>) -> DispatchReport {
+        let mut report = DispatchReport {
+            dry_run: self.dry_run,
+            entries: vec![],
+        };
+        for remind in merge_by_label(reminds) {
+            self.dispatch_one(&remind, &mut report).await;
+        }
+        report
+    }
+
+    async fn dispatch_one(&self, remind: &Remind, report: &mut DispatchReport) {
+        let entry = |channel, outcome| DispatchEntry {
+            label: remind.label.clone(),
+            debtor_id: remind.debtor.debtor_id.clone(),
+            channel,
+            outcome,
+        };
+
+        let (debts, inactive): (Vec<&Debt>, Vec<&Debt>) =
+            remind.debts.iter().partition(|d| is_dispatchable(d));
+        for debt in inactive {
+            report.entries.push(entry(
+                None,
+                DispatchOutcome::Skipped(SkipReason::InactiveDebt {
+                    debt_id: debt.debt_id.clone(),
+                    status: debt.debt_status.status.clone(),
+                }),
+            ));
+        }
+        if debts.is_empty() {
+            report.entries.push(entry(
+                None,
+                DispatchOutcome::Skipped(SkipReason::NoActiveDebts),
+            ));
+            return;
+        }
+
+        let context = TemplateContext::new(remind, debts, &self.business_time_zone);
+        for channel in &self.channels {
+            let kind = channel.kind();
+            let outcome = match self.prepare(channel.as_ref(), &context) {
+                Ok(None) => DispatchOutcome::Skipped(SkipReason::NoRecipient),
+                Ok(Some(message)) if self.dry_run => DispatchOutcome::Planned(message),
+                Ok(Some(message)) => match channel.send(&message).await {
+                    Ok(()) => DispatchOutcome::Sent(message),
+                    Err(e) => {
+                        log::warn!("failed to send {} to {}: {}", kind, message.label, e);
+                        DispatchOutcome::Failed(e)
+                    }
+                },
+                Err(e) => DispatchOutcome::Failed(e),
+            };
+            report.entries.push(entry(Some(kind), outcome));
+        }
+    }
+
+    /// 文面を作り、チャネルが受け付けるか確かめる。宛先がなければ `None`
+    fn prepare(
+        &self,
+        channel: &dyn Channel,
+        context: &TemplateContext<'_>,
+    ) -> Result<Option<Message>, DispatchError> {
+        let kind = channel.kind();
+        let Some(to) = kind.recipient(context.debtor) else {
+            return Ok(None);
+        };
+        let rendered = self.engine.render(kind, context)?;
+        let message = Message {
+            label: context.label.to_string(),
+            kind,
+            debtor_id: context.debtor.debtor_id.clone(),
+            name: context.debtor.basic_information.name.clone(),
+            to,
+            subject: rendered.subject,
+            body: rendered.body,
+            debt_ids: context.debts.iter().map(|d| d.debt_id.clone()).collect(),
+        };
+        channel.check(&message)?;
+        Ok(Some(message))
+    }
+}
+
+/// 督促の対象になるステータスか
+fn is_dispatchable(debt: &Debt) -> bool {
+    matches!(
+        debt.debt_status.status,
+        DebtStatusVariable::Active | DebtStatusVariable::AutoActivated
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::fixture;
+
+    fn sample_template() -> SimpleTemplate {
+        SimpleTemplate::new()
+            .with_template(
+                ChannelKind::Email,
+                Some("お支払いのお願い"),
+                "{{debtor.basic_information.name}} 様 {{custom_fields.item_name}} {{total_outstanding}}",
+            )
+            .with_template(ChannelKind::Sms, None, "{{total_outstanding}}をお支払いください")
+    }
+
+    /// 送った文面を覚えておくチャネル
+    fn recording(kind: ChannelKind, sent: &Arc<Mutex<Vec<Message>>>) -> impl Channel {
+        let sent = sent.clone();
+        let record = move |message: Message| {
+            sent.lock().unwrap().push(message);
+            async { Ok(()) }
+        };
+        match kind {
+            ChannelKind::Sms => Box::new(SmsChannel::new(record)) as Box<dyn Channel>,
+            _ => Box::new(EmailChannel::new(record)),
+        }
+    }
+
+    #[test]
+    fn test_recipient() -> anyhow::Result<()> {
+        let mut debtor = fixture::reminds().remove(0).debtor;
+        assert_eq!(
+            ChannelKind::Email.recipient(&debtor),
+            Some("sample@example.com".into())
+        );
+        assert_eq!(
+            ChannelKind::Sms.recipient(&debtor),
+            Some("09012345678".into())
+        );
+        assert_eq!(
+            ChannelKind::PostalLetter.recipient(&debtor),
+            Some("〒3336666 東京都xx区xx町x-x-x".into())
+        );
+
+        debtor.email.email = "".into();
+        debtor.phone_number.mobile_number = Some("0312345678".into());
+        assert_eq!(ChannelKind::Email.recipient(&debtor), None);
+        assert_eq!(ChannelKind::Sms.recipient(&debtor), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() -> anyhow::Result<()> {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let dispatcher = Dispatcher::new(sample_template())
+            .with_channel(recording(ChannelKind::Email, &sent))
+            .with_channel(recording(ChannelKind::Sms, &sent));
+
+        let report = dispatcher.dispatch(fixture::reminds()).await;
+        assert!(report.is_all_success(), "{}", report);
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(report.messages().cloned().collect::<Vec<_>>(), *sent);
+        assert_eq!(sent[0].kind, ChannelKind::Email);
+        assert_eq!(sent[0].subject.as_deref(), Some("お支払いのお願い"));
+        assert_eq!(sent[0].body, "test name 様 iPhoneSE 12 10,600円");
+        assert_eq!(sent[0].debt_ids, vec!["test external id6".to_string()]);
+        assert_eq!(sent[1].to, "09012345678");
+
+        // 1件目の解約済みの債権と、2件目のリマインドは送らない
+        assert_eq!(
+            report
+                .skipped()
+                .map(|(entry, reason)| (entry.label.as_str(), reason.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "test external id3---2022-03",
+                    SkipReason::InactiveDebt {
+                        debt_id: "test external id5".into(),
+                        status: DebtStatusVariable::DebtCancelled,
+                    }
+                ),
+                (
+                    "test external id4---2022-03",
+                    SkipReason::InactiveDebt {
+                        debt_id: "test external id7".into(),
+                        status: DebtStatusVariable::Repaid,
+                    }
+                ),
+                (
+                    "test external id4---2022-03",
+                    SkipReason::InactiveDebt {
+                        debt_id: "test external id8".into(),
+                        status: DebtStatusVariable::BadDebtFixed,
+                    }
+                ),
+                ("test external id4---2022-03", SkipReason::NoActiveDebts),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() -> anyhow::Result<()> {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let dispatcher = Dispatcher::new(sample_template())
+            .with_channel(recording(ChannelKind::Email, &sent))
+            .with_channel(PostalLetterChannel::new(std::env::temp_dir()))
+            .with_dry_run(true);
+
+        let report = dispatcher.dispatch(fixture::reminds()).await;
+        assert!(sent.lock().unwrap().is_empty());
+        assert_eq!(report.messages().count(), 1);
+        // 郵送のテンプレートがない
+        assert_matches!(
+            report.failed().collect::<Vec<_>>()[..],
+            [(entry, DispatchError::Template(_))] if entry.channel == Some(ChannelKind::PostalLetter)
+        );
+        assert!(report
+            .to_string()
+            .starts_with("[dry run] 1 planned, 4 skipped, 1 failed\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,387 @@
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+
+use futures::future::{self, BoxFuture};
+use futures::FutureExt;
+
+use crate::dispatch::{ChannelKind, DispatchError, Message};
+
+/// 文面の送り先
+pub trait Channel: Send + Sync {
+    /// 宛先と文面の種類を決める
+    fn kind(&self) -> ChannelKind;
+
+    /// 送る前の確認。dry runでも呼ばれる
+    fn check(&self, _message: &Message) -> Result<(), DispatchError> {
+        Ok(())
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<(), DispatchError>>;
+}
+
+impl<C: Channel + ?Sized> Channel for Box<C> {
+    fn kind(&self) -> ChannelKind {
+        (**self).kind()
+    }
+
+    fn check(&self, message: &Message) -> Result<(), DispatchError> {
+        (**self).check(message)
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<(), DispatchError>> {
+        (**self).send(message)
+    }
+}
+
+type SendFn = Box<dyn Fn(Message) -> BoxFuture<'static, Result<(), DispatchError>> + Send + Sync>;
+
+fn boxed_send<F, Fut>(send: F) -> SendFn
+where
+    F: Fn(Message) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DispatchError>> + Send + 'static,
+{
+    Box::new(move |message| send(message).boxed())
+}
+
+/// メール。実際の送信 (SMTPやメール配信サービス) は `send` に任せる
+pub struct EmailChannel {
+    send: SendFn,
+}
+
+impl EmailChannel {
+    pub fn new<F, Fut>(send: F) -> Self
+    where
+        F: Fn(Message) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), DispatchError>> + Send + 'static,
+    {
+        Self {
+            send: boxed_send(send),
+        }
+    }
+}
+
+impl Channel for EmailChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Email
+    }
+
+    /// 件名が必要
+    fn check(&self, message: &Message) -> Result<(), DispatchError> {
+        match message.subject.as_deref() {
+            Some(s) if !s.trim().is_empty() => Ok(()),
+            _ => Err(DispatchError::Rejected {
+                kind: ChannelKind::Email,
+                message: "subject is required".into(),
+            }),
+        }
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<(), DispatchError>> {
+        (self.send)(message.clone())
+    }
+}
+
+/// SMS。実際の送信は `send` に任せる
+pub struct SmsChannel {
+    send: SendFn,
+    max_chars: usize,
+}
+
+impl SmsChannel {
+    /// 国内キャリアの長文SMSの上限 (全角670文字)
+    pub const DEFAULT_MAX_CHARS: usize = 670;
+
+    pub fn new<F, Fut>(send: F) -> Self
+    where
+        F: Fn(Message) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), DispatchError>> + Send + 'static,
+    {
+        Self {
+            send: boxed_send(send),
+            max_chars: Self::DEFAULT_MAX_CHARS,
+        }
+    }
+
+    /// 本文の最大文字数
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+}
+
+impl Channel for SmsChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::Sms
+    }
+
+    fn check(&self, message: &Message) -> Result<(), DispatchError> {
+        let len = message.body.chars().count();
+        if len > self.max_chars {
+            return Err(DispatchError::Rejected {
+                kind: ChannelKind::Sms,
+                message: format!("body has {} chars (max {})", len, self.max_chars),
+            });
+        }
+        Ok(())
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<(), DispatchError>> {
+        (self.send)(message.clone())
+    }
+}
+
+/// 郵送用に、`dir` に1通1ファイルのテキストを書き出す。ファイル名は `label` から作り、
+/// 同じファイルがすでにあれば上書きせず `DispatchError::Send` にする
+pub struct PostalLetterChannel {
+    dir: PathBuf,
+}
+
+impl PostalLetterChannel {
+    /// 拡張子を除いたファイル名の最大バイト数。多くのファイルシステムの上限 (255バイト) に収める
+    const MAX_NAME_LEN: usize = 200;
+
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `message` を書き出すファイル。英数字と `-` `_` 以外は `%XX` (UTF-8のバイト) にするので、
+    /// 違う `label` が同じファイル名になることはない。長すぎる名前は切り詰めて
+    /// `.` とラベル全体のハッシュを付ける (`.` はエスケープされるので他の名前とは重ならない)
+    pub fn path(&self, message: &Message) -> PathBuf {
+        let mut name = String::with_capacity(message.label.len());
+        for b in message.label.bytes() {
+            match b {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => name.push(b as char),
+                _ => name.push_str(&format!("%{:02X}", b)),
+            }
+        }
+        if name.len() > Self::MAX_NAME_LEN {
+            let hash = format!("{:016x}", fnv1a(message.label.as_bytes()));
+            let mut end = Self::MAX_NAME_LEN - hash.len() - 1;
+            // `%XX` の途中で切らない
+            while let Some(i) = name[..end].rfind('%').filter(|i| end - i < 3) {
+                end = i;
+            }
+            name = format!("{}.{}", &name[..end], hash);
+        }
+        self.dir.join(format!("{}.txt", name))
+    }
+
+    fn write(&self, message: &Message) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.path(message))?;
+        write!(
+            file,
+            "{}\n{} 様\n\n{}\n",
+            message.to, message.name, message.body
+        )
+    }
+}
+
+impl Channel for PostalLetterChannel {
+    fn kind(&self) -> ChannelKind {
+        ChannelKind::PostalLetter
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<(), DispatchError>> {
+        future::ready(self.write(message).map_err(DispatchError::from)).boxed()
+    }
+}
+
+/// ビルドやプラットフォームによらず同じ値になるハッシュ (FNV-1a 64bit)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `path` に `Message` を1行1件のJSONで追記する。テスト・確認用
+pub struct FileChannel {
+    kind: ChannelKind,
+    path: PathBuf,
+}
+
+impl FileChannel {
+    pub fn new(kind: ChannelKind, path: impl Into<PathBuf>) -> Self {
+        Self {
+            kind,
+            path: path.into(),
+        }
+    }
+
+    fn append(&self, message: &Message) -> Result<(), DispatchError> {
+        let line =
+            serde_json::to_string(message).map_err(|e| DispatchError::Send(e.to_string()))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+impl Channel for FileChannel {
+    fn kind(&self) -> ChannelKind {
+        self.kind
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<(), DispatchError>> {
+        future::ready(self.append(message)).boxed()
+    }
+}
+
+/// 標準出力に書く。テスト・確認用
+pub struct StdoutChannel {
+    kind: ChannelKind,
+}
+
+impl StdoutChannel {
+    pub fn new(kind: ChannelKind) -> Self {
+        Self { kind }
+    }
+}
+
+impl Channel for StdoutChannel {
+    fn kind(&self) -> ChannelKind {
+        self.kind
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<(), DispatchError>> {
+        println!("--- {} to {} ({})", message.kind, message.to, message.label);
+        if let Some(subject) = &message.subject {
+            println!("subject: {}", subject);
+        }
+        println!("{}", message.body);
+        future::ready(Ok(())).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn sample_message(kind: ChannelKind) -> Message {
+        Message {
+            label: "test external id3---2022-03".into(),
+            kind,
+            debtor_id: "test external id3".into(),
+            name: "test name".into(),
+            to: "〒3336666 東京都xx区xx町x-x-x".into(),
+            subject: None,
+            body: "10,600円をお支払いください".into(),
+            debt_ids: vec!["test external id6".into()],
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("lecto-dispatch-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_check() {
+        let email = EmailChannel::new(|_| async { Ok(()) });
+        assert_matches!(
+            email.check(&sample_message(ChannelKind::Email)),
+            Err(DispatchError::Rejected { .. })
+        );
+
+        let sms = SmsChannel::new(|_| async { Ok(()) });
+        assert_eq!(sms.check(&sample_message(ChannelKind::Sms)), Ok(()));
+        assert_matches!(
+            sms.with_max_chars(5)
+                .check(&sample_message(ChannelKind::Sms)),
+            Err(DispatchError::Rejected {
+                kind: ChannelKind::Sms,
+                ..
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_postal_letter() -> anyhow::Result<()> {
+        let dir = temp_path("letters");
+        let channel = PostalLetterChannel::new(&dir);
+        let message = sample_message(ChannelKind::PostalLetter);
+        channel.send(&message).await?;
+
+        let path = channel.path(&message);
+        assert_eq!(path, dir.join("test%20external%20id3---2022-03.txt"));
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "〒3336666 東京都xx区xx町x-x-x\ntest name 様\n\n10,600円をお支払いください\n"
+        );
+
+        // 以前は同じ名前になっていたラベルも別のファイルにする
+        let other = Message {
+            label: "test_external_id3---2022-03".into(),
+            ..message.clone()
+        };
+        assert_ne!(channel.path(&other), path);
+        let japanese = Message {
+            label: "債務者3".into(),
+            ..message.clone()
+        };
+        assert_eq!(
+            channel.path(&japanese),
+            dir.join("%E5%82%B5%E5%8B%99%E8%80%853.txt")
+        );
+
+        // 上書きしない
+        assert_matches!(channel.send(&message).await, Err(DispatchError::Send(_)));
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_postal_letter_long_label() -> anyhow::Result<()> {
+        let dir = temp_path("long-letters");
+        let channel = PostalLetterChannel::new(&dir);
+        let message = Message {
+            label: format!("{}---2022-03", "債務者".repeat(20)),
+            ..sample_message(ChannelKind::PostalLetter)
+        };
+        let other = Message {
+            label: format!("{}---2022-04", "債務者".repeat(20)),
+            ..message.clone()
+        };
+        channel.send(&message).await?;
+        channel.send(&other).await?;
+
+        let path = channel.path(&message);
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.len() <= 255);
+        assert!(name.starts_with("%E5%82%B5"));
+        assert_ne!(path, channel.path(&other));
+        assert!(path.exists());
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file() -> anyhow::Result<()> {
+        let path = temp_path("messages.jsonl");
+        let channel = FileChannel::new(ChannelKind::Sms, &path);
+        channel.send(&sample_message(ChannelKind::Sms)).await?;
+        channel.send(&sample_message(ChannelKind::Sms)).await?;
+
+        let written = fs::read_to_string(&path)?;
+        let lines: Vec<serde_json::Value> = written
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["kind"], "sms");
+        assert_eq!(lines[0]["debt_ids"][0], "test external id6");
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
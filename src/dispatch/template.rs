@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use chrono::{FixedOffset, NaiveDate};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::debt::Debt;
+use crate::debtor::Debtor;
+use crate::dispatch::{ChannelKind, DispatchError};
+use crate::remind_group::remind::Remind;
+use crate::Yen;
+
+/// テンプレートに渡す値。リマインド1件分で、`debts` は送る対象の債権だけ
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemplateContext<'a> {
+    pub label: &'a str,
+    pub debtor: &'a Debtor,
+    pub debts: Vec<&'a Debt>,
+    /// 各債権の `custom_fields` をまとめたもの。同じキーは先の債権を優先する
+    pub custom_fields: HashMap<String, String>,
+    /// `12,345円` の形。合計があふれたら `None` で、テンプレートで使うと描画に失敗する
+    pub total_outstanding: Option<String>,
+    /// 最も早い支払期日 (業務タイムゾーン)
+    pub repayment_due_date: Option<NaiveDate>,
+}
+
+impl<'a> TemplateContext<'a> {
+    pub fn new(remind: &'a Remind, debts: Vec<&'a Debt>, tz: &FixedOffset) -> Self {
+        let mut custom_fields = HashMap::new();
+        for debt in &debts {
+            for (k, v) in &debt.custom_fields {
+                custom_fields.entry(k.clone()).or_insert_with(|| v.clone());
+            }
+        }
+        let total_outstanding = debts
+            .iter()
+            .map(|d| d.total_outstanding())
+            .try_fold(Yen::ZERO, |acc, v| acc.checked_add(v?))
+            .map(|total| total.to_string());
+        let repayment_due_date = debts.iter().map(|d| d.repayment_due_date(tz)).min();
+        Self {
+            label: &remind.label,
+            debtor: &remind.debtor,
+            debts,
+            custom_fields,
+            total_outstanding,
+            repayment_due_date,
+        }
+    }
+}
+
+/// 描画した文面。件名はメールのみ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendered {
+    pub subject: Option<String>,
+    pub body: String,
+}
+
+/// 文面の作り方。Tera や Handlebars を使う場合は `TemplateContext` をそのまま渡せばよい
+pub trait TemplateEngine: Send + Sync {
+    fn render(
+        &self,
+        kind: ChannelKind,
+        context: &TemplateContext<'_>,
+    ) -> Result<Rendered, DispatchError>;
+}
+
+/// `{{debtor.basic_information.name}}` のように `TemplateContext` の値を埋め込むだけのテンプレート。
+/// パスは `.` 区切りで、配列は `debts.0.debt_id` のように番号で指す。
+/// 値がない (`null`) プレースホルダーは空欄で送らないよう `DispatchError::Template` にする
+#[derive(Debug, Clone, Default)]
+pub struct SimpleTemplate {
+    templates: HashMap<ChannelKind, (Option<String>, String)>,
+}
+
+impl SimpleTemplate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `kind` 用の件名と本文
+    pub fn with_template(
+        mut self,
+        kind: ChannelKind,
+        subject: Option<&str>,
+        body: impl Into<String>,
+    ) -> Self {
+        self.templates
+            .insert(kind, (subject.map(str::to_string), body.into()));
+        self
+    }
+}
+
+impl TemplateEngine for SimpleTemplate {
+    fn render(
+        &self,
+        kind: ChannelKind,
+        context: &TemplateContext<'_>,
+    ) -> Result<Rendered, DispatchError> {
+        let (subject, body) = self
+            .templates
+            .get(&kind)
+            .ok_or_else(|| DispatchError::Template(format!("no template for {}", kind)))?;
+        let context =
+            serde_json::to_value(context).map_err(|e| DispatchError::Template(e.to_string()))?;
+        Ok(Rendered {
+            subject: subject
+                .as_deref()
+                .map(|s| substitute(s, &context))
+                .transpose()?,
+            body: substitute(body, &context)?,
+        })
+    }
+}
+
+fn substitute(template: &str, context: &Value) -> Result<String, DispatchError> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| DispatchError::Template(format!("unclosed {{{{ in {:?}", template)))?;
+        out.push_str(&lookup(context, after[..end].trim())?);
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn lookup(context: &Value, path: &str) -> Result<String, DispatchError> {
+    let value = path
+        .split('.')
+        .try_fold(context, |value, key| match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+        .ok_or_else(|| DispatchError::Template(format!("unknown placeholder {:?}", path)))?;
+    match value {
+        Value::Null => Err(DispatchError::Template(format!(
+            "placeholder {:?} has no value",
+            path
+        ))),
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Array(_) | Value::Object(_) => Err(DispatchError::Template(format!(
+            "placeholder {:?} is not a single value",
+            path
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::fixture;
+    use crate::tz::JST;
+
+    #[test]
+    fn test_render() -> anyhow::Result<()> {
+        let remind = fixture::reminds().remove(0);
+        let context = TemplateContext::new(&remind, remind.debts.iter().collect(), &JST);
+        assert_eq!(context.total_outstanding.as_deref(), Some("21,200円"));
+        assert_eq!(
+            context.repayment_due_date,
+            NaiveDate::from_ymd_opt(2022, 3, 17)
+        );
+
+        let template = SimpleTemplate::new().with_template(
+            ChannelKind::Email,
+            Some("{{ debtor.basic_information.name }} 様"),
+            "{{debts.1.debt_id}} {{custom_fields.item_name}} {{repayment_due_date}}.",
+        );
+        assert_eq!(
+            template.render(ChannelKind::Email, &context)?,
+            Rendered {
+                subject: Some("test name 様".into()),
+                body: "test external id6 iPhoneSE 12 2022-03-17.".into(),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_overflowed_total() {
+        let remind = fixture::reminds().remove(0);
+        let mut context = TemplateContext::new(&remind, remind.debts.iter().collect(), &JST);
+        context.total_outstanding = None;
+
+        // 金額が空欄の督促は送らない
+        assert_matches!(
+            SimpleTemplate::new()
+                .with_template(ChannelKind::Sms, None, "{{total_outstanding}}をお支払いください")
+                .render(ChannelKind::Sms, &context),
+            Err(DispatchError::Template(m)) if m.contains("total_outstanding")
+        );
+    }
+
+    #[test]
+    fn test_render_errors() -> anyhow::Result<()> {
+        let remind = fixture::reminds().remove(0);
+        let context = TemplateContext::new(&remind, remind.debts.iter().collect(), &JST);
+        let render = |body: &str| {
+            SimpleTemplate::new()
+                .with_template(ChannelKind::Sms, None, body)
+                .render(ChannelKind::Sms, &context)
+        };
+
+        assert_matches!(render("{{custom_fields.missing}}"), Err(DispatchError::Template(m)) if m.contains("missing"));
+        assert_matches!(render("{{debtor}}"), Err(DispatchError::Template(_)));
+        assert_matches!(render("{{label"), Err(DispatchError::Template(_)));
+        assert_matches!(
+            render("{{debtor.basic_information.birth_date}}"),
+            Err(DispatchError::Template(m)) if m.contains("no value")
+        );
+        assert_matches!(
+            SimpleTemplate::new().render(ChannelKind::Sms, &context),
+            Err(DispatchError::Template(_))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,478 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::InvalidHeaderValue;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::custom_fields::CustomFieldError;
+use crate::debt_status::DebtStatusVariable;
+use crate::idempotency::IdempotencyKey;
+use crate::validation::ValidationErrors;
+
+pub type Result<T> = std::result::Result<T, LectoError>;
+
+/// Lectoがエラー時に返すbody `{"errors": [...]}`
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+pub struct ErrorResponse {
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+/// TODO: debtorでもdebtsでも使えるようにrequest,responseをStringにしているがDebtor以外の型ができたタイミングでGenericsにしたほうがいい気がする
+#[derive(thiserror::Error, Debug)]
+pub enum LectoError {
+    #[error("Status: {status} Res: {response:#?}")]
+    BadRequest {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+    },
+    #[error("Status: {status} Res: {response:#?}")]
+    Unauthorized {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+    },
+    #[error("Status: {status} Res: {response:#?}")]
+    Forbidden {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+    },
+    #[error("Status: {status} Res: {response:#?}")]
+    NotFound {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+    },
+    #[error("Status: {status} Res: {response:#?}")]
+    Conflict {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+    },
+    #[error("Status: {status} Res: {response:#?}")]
+    UnprocessableEntity {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+    },
+    #[error("Status: {status} Retry-After: {retry_after:?} Res: {response:#?}")]
+    TooManyRequests {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+        retry_after: Option<Duration>,
+    },
+    #[error("Status: {status} Res: {response:#?}")]
+    InternalServerError {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+    },
+    /// 500以外の5xx (502, 503, 504など)
+    #[error("Status: {status} Res: {response:#?}")]
+    ServerError {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+    },
+    /// 上記のどれにも当てはまらないステータス
+    #[error("Unexpected status. Status: {status} Req: {request} Res: {response:#?}")]
+    UnexpectedStatus {
+        status: StatusCode,
+        request: String,
+        response: String,
+        body: Option<ErrorResponse>,
+        /// 書き込みリクエストで送った `Idempotency-Key`
+        idempotency_key: Option<String>,
+    },
+    #[error("Transport error: {source}")]
+    Transport {
+        #[source]
+        source: reqwest::Error,
+        idempotency_key: Option<String>,
+    },
+    #[error("Failed to decode response: {source} Res: {body:#?}")]
+    Decode {
+        source: serde_json::Error,
+        body: String,
+    },
+    #[error("Invalid url: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("Invalid header value: {0}")]
+    InvalidHeader(#[from] InvalidHeaderValue),
+    #[error("Failed to build http client: {0}")]
+    Builder(reqwest::Error),
+    #[error("Invalid client config: {0}")]
+    Config(String),
+    /// `validate_before_send` が有効なときに送信前の確認で弾かれた
+    #[error("Validation failed: {0}")]
+    Validation(#[from] ValidationErrors),
+    /// `custom_fields` と利用側の型との変換に失敗した
+    #[error("Invalid custom field: {0}")]
+    CustomField(#[from] CustomFieldError),
+    /// 現在のステータスから遷移できないステータスを指定した
+    #[error("Illegal debt status transition for {debt_id}: {from:?} -> {to:?}")]
+    IllegalTransition {
+        debt_id: String,
+        from: DebtStatusVariable,
+        to: DebtStatusVariable,
+    },
+    /// strict モードでレスポンスにこのクライアントが知らない値があった
+    #[error("Unknown value in response. {field}: {value}")]
+    UnknownValue { field: String, value: String },
+    /// ページングを続けられない (次ページのURLが `base_url` と別のホスト、同じページの繰り返しなど)
+    #[error("Pagination error: {0}")]
+    Pagination(String),
+    /// バルク登録でチャンク全体が失敗した。チャンク内の全件が同じエラーを共有する
+    #[error("Bulk chunk failed: {0}")]
+    BulkChunkFailed(Arc<LectoError>),
+}
+
+impl From<reqwest::Error> for LectoError {
+    fn from(source: reqwest::Error) -> Self {
+        Self::Transport {
+            source,
+            idempotency_key: None,
+        }
+    }
+}
+
+impl LectoError {
+    /// レスポンスのステータスから対応するエラーを組み立てる
+    pub fn from_status(
+        status: StatusCode,
+        request: String,
+        response: String,
+        retry_after: Option<Duration>,
+    ) -> Self {
+        let body = serde_json::from_str::<ErrorResponse>(&response).ok();
+        match status {
+            StatusCode::BAD_REQUEST => Self::BadRequest {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+            },
+            StatusCode::UNAUTHORIZED => Self::Unauthorized {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+            },
+            StatusCode::FORBIDDEN => Self::Forbidden {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+            },
+            StatusCode::NOT_FOUND => Self::NotFound {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+            },
+            StatusCode::CONFLICT => Self::Conflict {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+            },
+            StatusCode::UNPROCESSABLE_ENTITY => Self::UnprocessableEntity {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+            },
+            StatusCode::TOO_MANY_REQUESTS => Self::TooManyRequests {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+                retry_after,
+            },
+            StatusCode::INTERNAL_SERVER_ERROR => Self::InternalServerError {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+            },
+            s if s.is_server_error() => Self::ServerError {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+            },
+            _ => Self::UnexpectedStatus {
+                status,
+                request,
+                response,
+                body,
+                idempotency_key: None,
+            },
+        }
+    }
+
+    /// HTTPレスポンス由来のエラーであればそのステータス
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::BadRequest { status, .. }
+            | Self::Unauthorized { status, .. }
+            | Self::Forbidden { status, .. }
+            | Self::NotFound { status, .. }
+            | Self::Conflict { status, .. }
+            | Self::UnprocessableEntity { status, .. }
+            | Self::TooManyRequests { status, .. }
+            | Self::InternalServerError { status, .. }
+            | Self::ServerError { status, .. }
+            | Self::UnexpectedStatus { status, .. } => Some(*status),
+            Self::Transport { source, .. } => source.status(),
+            _ => None,
+        }
+    }
+
+    /// `{"errors": [...]}` としてパースできたレスポンスbody
+    pub fn error_response(&self) -> Option<&ErrorResponse> {
+        match self {
+            Self::BadRequest { body, .. }
+            | Self::Unauthorized { body, .. }
+            | Self::Forbidden { body, .. }
+            | Self::NotFound { body, .. }
+            | Self::Conflict { body, .. }
+            | Self::UnprocessableEntity { body, .. }
+            | Self::TooManyRequests { body, .. }
+            | Self::InternalServerError { body, .. }
+            | Self::ServerError { body, .. }
+            | Self::UnexpectedStatus { body, .. } => body.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// 書き込みリクエストで送った `Idempotency-Key`。同じキーで送り直せば重複登録にならない
+    pub fn idempotency_key(&self) -> Option<&str> {
+        match self {
+            Self::BadRequest {
+                idempotency_key, ..
+            }
+            | Self::Unauthorized {
+                idempotency_key, ..
+            }
+            | Self::Forbidden {
+                idempotency_key, ..
+            }
+            | Self::NotFound {
+                idempotency_key, ..
+            }
+            | Self::Conflict {
+                idempotency_key, ..
+            }
+            | Self::UnprocessableEntity {
+                idempotency_key, ..
+            }
+            | Self::TooManyRequests {
+                idempotency_key, ..
+            }
+            | Self::InternalServerError {
+                idempotency_key, ..
+            }
+            | Self::ServerError {
+                idempotency_key, ..
+            }
+            | Self::UnexpectedStatus {
+                idempotency_key, ..
+            }
+            | Self::Transport {
+                idempotency_key, ..
+            } => idempotency_key.as_deref(),
+            Self::BulkChunkFailed(e) => e.idempotency_key(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn with_idempotency_key(mut self, key: &IdempotencyKey) -> Self {
+        match &mut self {
+            Self::BadRequest {
+                idempotency_key, ..
+            }
+            | Self::Unauthorized {
+                idempotency_key, ..
+            }
+            | Self::Forbidden {
+                idempotency_key, ..
+            }
+            | Self::NotFound {
+                idempotency_key, ..
+            }
+            | Self::Conflict {
+                idempotency_key, ..
+            }
+            | Self::UnprocessableEntity {
+                idempotency_key, ..
+            }
+            | Self::TooManyRequests {
+                idempotency_key, ..
+            }
+            | Self::InternalServerError {
+                idempotency_key, ..
+            }
+            | Self::ServerError {
+                idempotency_key, ..
+            }
+            | Self::UnexpectedStatus {
+                idempotency_key, ..
+            }
+            | Self::Transport {
+                idempotency_key, ..
+            } => *idempotency_key = Some(key.to_string()),
+            _ => {}
+        }
+        self
+    }
+
+    pub fn is_auth_error(&self) -> bool {
+        matches!(self, Self::Unauthorized { .. } | Self::Forbidden { .. })
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        matches!(
+            self,
+            Self::InternalServerError { .. } | Self::ServerError { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(StatusCode::BAD_REQUEST)]
+    #[case(StatusCode::UNAUTHORIZED)]
+    #[case(StatusCode::FORBIDDEN)]
+    #[case(StatusCode::NOT_FOUND)]
+    #[case(StatusCode::CONFLICT)]
+    #[case(StatusCode::UNPROCESSABLE_ENTITY)]
+    #[case(StatusCode::TOO_MANY_REQUESTS)]
+    #[case(StatusCode::INTERNAL_SERVER_ERROR)]
+    #[case(StatusCode::BAD_GATEWAY)]
+    #[case(StatusCode::SERVICE_UNAVAILABLE)]
+    #[case(StatusCode::IM_A_TEAPOT)]
+    fn test_from_status_keeps_status(#[case] status: StatusCode) {
+        let e = LectoError::from_status(status, "req".into(), "res".into(), None);
+        assert_eq!(e.status(), Some(status));
+    }
+
+    #[test]
+    fn test_from_status_variants() {
+        let e = |s| LectoError::from_status(s, String::new(), String::new(), None);
+
+        assert_matches!(e(StatusCode::UNAUTHORIZED), LectoError::Unauthorized { .. });
+        assert_matches!(e(StatusCode::FORBIDDEN), LectoError::Forbidden { .. });
+        assert_matches!(e(StatusCode::NOT_FOUND), LectoError::NotFound { .. });
+        assert_matches!(e(StatusCode::CONFLICT), LectoError::Conflict { .. });
+        assert_matches!(e(StatusCode::BAD_GATEWAY), LectoError::ServerError { .. });
+        assert_matches!(
+            e(StatusCode::IM_A_TEAPOT),
+            LectoError::UnexpectedStatus { .. }
+        );
+        assert!(e(StatusCode::FORBIDDEN).is_auth_error());
+        assert!(e(StatusCode::SERVICE_UNAVAILABLE).is_server_error());
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let e = LectoError::from_status(
+            StatusCode::TOO_MANY_REQUESTS,
+            String::new(),
+            r#"{"errors": ["rate limited"]}"#.into(),
+            Some(Duration::from_secs(3)),
+        );
+
+        assert_eq!(
+            e.error_response(),
+            Some(&ErrorResponse {
+                errors: vec!["rate limited".into()]
+            })
+        );
+        assert_matches!(e, LectoError::TooManyRequests { retry_after, .. } => {
+            assert_eq!(retry_after, Some(Duration::from_secs(3)));
+        });
+    }
+
+    #[test]
+    fn test_with_idempotency_key() {
+        let key = IdempotencyKey::new("key-1");
+        let e = LectoError::from_status(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            String::new(),
+            String::new(),
+            None,
+        );
+        assert_eq!(e.idempotency_key(), None);
+
+        let e = e.with_idempotency_key(&key);
+        assert_eq!(e.idempotency_key(), Some("key-1"));
+        assert_eq!(
+            LectoError::BulkChunkFailed(Arc::new(e)).idempotency_key(),
+            Some("key-1")
+        );
+        assert_eq!(
+            LectoError::Config(String::new())
+                .with_idempotency_key(&key)
+                .idempotency_key(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unparsable_error_response() {
+        let e = LectoError::from_status(
+            StatusCode::BAD_GATEWAY,
+            String::new(),
+            "<html>bad gateway</html>".into(),
+            None,
+        );
+        assert_eq!(e.error_response(), None);
+    }
+}
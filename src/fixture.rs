@@ -1,9 +1,27 @@
-use chrono::{Local, NaiveDate, TimeZone};
+use std::time::Duration;
+
+use chrono::{NaiveDate, TimeZone};
 use serde_json::json;
 
+use crate::client::Client;
 use crate::debt::{DebtRequest, PartnerRequest};
 use crate::debt_status::{DebtStatusRequest, DebtStatusVariable};
 use crate::debtor::{DebtorRawRequest, DebtorRequest, Gender};
+use crate::money::Yen;
+use crate::remind_group::remind::{Remind, RemindResponse};
+use crate::retry::RetryPolicy;
+use crate::tz::JST;
+
+/// リトライしない、モックサーバー向けのクライアント
+pub fn test_client(api_key: &str, base_url: String) -> Client {
+    Client::builder()
+        .api_key(api_key)
+        .base_url(base_url)
+        .timeout(Duration::from_secs(10))
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap()
+}
 
 pub fn debtor_request_sample_data() -> DebtorRequest {
     DebtorRequest {
@@ -29,11 +47,11 @@ pub fn debt_request_sample_data() -> DebtRequest {
     DebtRequest {
         debt_id: "1234-4321".into(),
         debtor_id: "5678".into(),
-        dealt_at: Local.with_ymd_and_hms(2021, 12, 1, 12, 13, 0).unwrap(),
-        debt_amount: 7400,
-        debt_fee: Some(540),
-        debt_delinquency_charge: Some(680),
-        repayment_due_at: Local.with_ymd_and_hms(2022, 3, 1, 23, 59, 59).unwrap(),
+        dealt_at: JST.with_ymd_and_hms(2021, 12, 1, 12, 13, 0).unwrap(),
+        debt_amount: Yen::new(7400),
+        debt_fee: Some(Yen::new(540)),
+        debt_delinquency_charge: Some(Yen::new(680)),
+        repayment_due_at: JST.with_ymd_and_hms(2022, 3, 1, 23, 59, 59).unwrap(),
         custom_fields: [
             ("lease_id".into(), "xxxx".into()),
             ("lease_contract_id".into(), "xxxxx".into()),
@@ -51,10 +69,10 @@ pub fn debt_request_sample_data() -> DebtRequest {
             name: Some("加盟店アメリケン".into()),
         }),
         debt_status: Some(DebtStatusRequest {
-            debt_id: "1234-5678".into(),
+            debt_id: "1234-4321".into(),
             status: Some(DebtStatusVariable::Repaid),
-            changed_at: Local.with_ymd_and_hms(2021, 11, 15, 12, 34, 0).unwrap(),
-            expire_at: Local.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap(),
+            changed_at: JST.with_ymd_and_hms(2021, 11, 15, 12, 34, 0).unwrap(),
+            expire_at: JST.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap(),
             status_id: None,
         }),
     }
@@ -64,8 +82,8 @@ pub fn debt_status_request_sample_data() -> DebtStatusRequest {
     DebtStatusRequest {
         debt_id: "1234-5678".into(),
         status: Some(DebtStatusVariable::Repaid),
-        changed_at: Local.with_ymd_and_hms(2021, 11, 15, 12, 34, 0).unwrap(),
-        expire_at: Local.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap(),
+        changed_at: JST.with_ymd_and_hms(2021, 11, 15, 12, 34, 0).unwrap(),
+        expire_at: JST.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap(),
         status_id: None,
     }
 }
@@ -139,3 +157,16 @@ pub fn lecto_debt_status_response() -> serde_json::Value {
         "status_id": "LECTO-400"
     })
 }
+
+/// `test-data/lecto-remind-groups-reminds.json` の中身。モックサーバーのレスポンス用
+pub fn remind_responses_json() -> String {
+    std::fs::read_to_string("test-data/lecto-remind-groups-reminds.json").unwrap()
+}
+
+pub fn remind_responses() -> Vec<RemindResponse> {
+    serde_json::from_str(&remind_responses_json()).unwrap()
+}
+
+pub fn reminds() -> Vec<Remind> {
+    remind_responses().into_iter().map(Remind::from).collect()
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// 書き込みリクエストに付ける `Idempotency-Key`。
+/// リトライ中は同じ値を使い回すので、1回目が実は登録されていても重複しない
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey(String);
+
+impl IdempotencyKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// ランダムなUUID (v4) を生成する
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 16];
+        bytes.iter_mut().for_each(|b| *b = fastrand::u8(..));
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        Self(format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        ))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for IdempotencyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for IdempotencyKey {
+    fn from(key: String) -> Self {
+        Self(key)
+    }
+}
+
+impl From<&str> for IdempotencyKey {
+    fn from(key: &str) -> Self {
+        Self(key.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let a = IdempotencyKey::generate();
+        let b = IdempotencyKey::generate();
+
+        assert_ne!(a, b);
+        let parts: Vec<usize> = a.as_str().split('-').map(str::len).collect();
+        assert_eq!(parts, vec![8, 4, 4, 4, 12]);
+        assert_eq!(&a.as_str()[14..15], "4");
+        assert!(a
+            .as_str()
+            .chars()
+            .all(|c| c == '-' || c.is_ascii_hexdigit()));
+    }
+}
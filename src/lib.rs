@@ -1,16 +1,41 @@
 pub mod client;
+pub mod custom_fields;
 pub mod debt;
 pub mod debt_status;
 pub mod debtor;
+pub mod dispatch;
+pub mod error;
+pub mod idempotency;
+pub mod money;
+pub mod normalize;
+pub mod pagination;
 pub mod remind_group;
+pub mod retry;
+pub mod scheduler;
+mod strict;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tz;
 pub mod util;
+pub mod validation;
 
 #[cfg(test)]
 pub mod fixture;
 
-pub use debt::{Debt, DebtRequest, Partner};
-pub use debt_status::{DebtStatus, DebtStatusRequest, DebtStatusVariable};
+pub use client::{BulkOptions, BulkReport, Client, ClientBuilder};
+pub use custom_fields::CustomFieldError;
+pub use debt::{Debt, DebtFilter, DebtPatchRequest, DebtRequest, DebtRequestBuilder, Partner};
+pub use debt_status::{
+    DebtStatus, DebtStatusRequest, DebtStatusVariable, StatusTimeline, TimelineIssue,
+};
 pub use debtor::{
-    Debtor, DebtorAddress, DebtorBasicInformation, DebtorEmail, DebtorPhoneNumber, DebtorRequest,
-    Gender,
+    Debtor, DebtorAddress, DebtorBasicInformation, DebtorEmail, DebtorFilter, DebtorPatchRequest,
+    DebtorPhoneNumber, DebtorRequest, Gender, KycDone,
 };
+pub use error::{LectoError, Result};
+pub use idempotency::IdempotencyKey;
+pub use money::Yen;
+pub use normalize::{FieldChange, Normalized};
+pub use pagination::collect_all;
+pub use retry::{Jitter, RetryPolicy};
+pub use validation::{FieldError, FieldErrorKind, ValidationErrors};
@@ -0,0 +1,114 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// 円建ての金額。JSONでは整数のまま読み書きする。
+/// 演算はオーバーフローすると `None` になる `checked_*` のみ用意している
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Yen(i64);
+
+impl Yen {
+    pub const ZERO: Yen = Yen(0);
+
+    pub const fn new(value: i64) -> Self {
+        Self(value)
+    }
+
+    pub const fn value(self) -> i64 {
+        self.0
+    }
+
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn checked_add(self, rhs: Yen) -> Option<Yen> {
+        self.0.checked_add(rhs.0).map(Yen)
+    }
+
+    pub fn checked_sub(self, rhs: Yen) -> Option<Yen> {
+        self.0.checked_sub(rhs.0).map(Yen)
+    }
+
+    pub fn checked_mul(self, rhs: i64) -> Option<Yen> {
+        self.0.checked_mul(rhs).map(Yen)
+    }
+
+    /// 合計。途中でオーバーフローしたら `None`
+    pub fn checked_sum(values: impl IntoIterator<Item = Yen>) -> Option<Yen> {
+        values
+            .into_iter()
+            .try_fold(Yen::ZERO, |acc, v| acc.checked_add(v))
+    }
+}
+
+impl From<i64> for Yen {
+    fn from(value: i64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Yen> for i64 {
+    fn from(value: Yen) -> Self {
+        value.0
+    }
+}
+
+/// `12,345円` の形で表示する
+impl fmt::Display for Yen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.0.unsigned_abs().to_string();
+        let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(c);
+        }
+        let sign = if self.0 < 0 { "-" } else { "" };
+        write!(f, "{}{}円", sign, grouped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[test]
+    fn test_serde_as_integer() -> anyhow::Result<()> {
+        assert_eq!(serde_json::to_string(&Yen::new(7400))?, "7400");
+        assert_eq!(serde_json::from_str::<Yen>("-680")?, Yen::new(-680));
+        Ok(())
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(
+            Yen::new(7400).checked_add(Yen::new(540)),
+            Some(Yen::new(7940))
+        );
+        assert_eq!(Yen::new(i64::MAX).checked_add(Yen::new(1)), None);
+        assert_eq!(Yen::new(i64::MIN).checked_sub(Yen::new(1)), None);
+        assert_eq!(Yen::new(500).checked_mul(3), Some(Yen::new(1500)));
+        assert_eq!(
+            Yen::checked_sum([Yen::new(1), Yen::new(2), Yen::new(3)]),
+            Some(Yen::new(6))
+        );
+        assert_eq!(Yen::checked_sum([Yen::new(i64::MAX), Yen::new(1)]), None);
+    }
+
+    #[rstest]
+    #[case(0, "0円")]
+    #[case(540, "540円")]
+    #[case(7400, "7,400円")]
+    #[case(1234567, "1,234,567円")]
+    #[case(-100000, "-100,000円")]
+    fn test_display(#[case] value: i64, #[case] expected: &str) {
+        assert_eq!(Yen::new(value).to_string(), expected);
+    }
+}
@@ -0,0 +1,174 @@
+/// 正規化で書き換えた項目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// 正規化した値と、書き換えた項目の一覧
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalized<T> {
+    pub value: T,
+    pub changes: Vec<FieldChange>,
+}
+
+impl<T> Normalized<T> {
+    pub fn is_changed(&self) -> bool {
+        !self.changes.is_empty()
+    }
+}
+
+/// `field` に `f` を適用し、値が変わったら `changes` に記録する
+pub(crate) fn apply(
+    changes: &mut Vec<FieldChange>,
+    field: &str,
+    value: &mut String,
+    f: fn(&str) -> String,
+) {
+    let after = f(value);
+    if after != *value {
+        changes.push(FieldChange {
+            field: field.to_string(),
+            before: std::mem::replace(value, after.clone()),
+            after,
+        });
+    }
+}
+
+/// 前後の空白 (全角を含む) を除く
+pub(crate) fn trim(value: &str) -> String {
+    value.trim().to_string()
+}
+
+/// 全角英数記号 (`！`〜`～`) と全角空白を半角にする
+pub(crate) fn to_half_width_ascii(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            '\u{3000}' => ' ',
+            c => c,
+        })
+        .collect()
+}
+
+fn is_dash(c: char) -> bool {
+    matches!(
+        c,
+        '-' | '\u{2010}'
+            | '\u{2011}'
+            | '\u{2012}'
+            | '\u{2013}'
+            | '\u{2014}'
+            | '\u{2015}'
+            | '\u{2212}'
+            | '\u{30FC}'
+            | '\u{FF0D}'
+            | '\u{FF70}'
+    )
+}
+
+/// 半角数字にし、ハイフン類・括弧・空白を除く (郵便番号・電話番号用)
+pub(crate) fn to_digits(value: &str) -> String {
+    to_half_width_ascii(value.trim())
+        .chars()
+        .filter(|c| !is_dash(*c) && !matches!(c, '(' | ')' | ' '))
+        .collect()
+}
+
+/// 半角カタカナ `｡` (U+FF61) から `ﾟ` (U+FF9F) に対応する全角文字
+const HALF_WIDTH_KANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+
+/// 濁点と合成できる文字。合成後は次のコードポイントになる
+const VOICEABLE: &str = "カキクケコサシスセソタチツテトハヒフヘホ";
+/// 半濁点と合成できる文字。合成後は2つ先のコードポイントになる
+const SEMI_VOICEABLE: &str = "ハヒフヘホ";
+
+/// 半角カタカナ・ひらがなを全角カタカナにする。濁点・半濁点は前の文字と合成する
+pub(crate) fn to_full_width_katakana(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.trim().chars() {
+        let c = match c {
+            '\u{FF61}'..='\u{FF9F}' => HALF_WIDTH_KANA
+                .chars()
+                .nth(c as usize - 0xFF61)
+                .unwrap_or(c),
+            '\u{3041}'..='\u{3096}' | '\u{309D}' | '\u{309E}' => {
+                char::from_u32(c as u32 + 0x60).unwrap_or(c)
+            }
+            c => c,
+        };
+
+        let composed = match (result.chars().last(), c) {
+            (Some('ウ'), '゛') => Some('ヴ'),
+            (Some(prev), '゛') if VOICEABLE.contains(prev) => char::from_u32(prev as u32 + 1),
+            (Some(prev), '゜') if SEMI_VOICEABLE.contains(prev) => char::from_u32(prev as u32 + 2),
+            _ => None,
+        };
+        match composed {
+            Some(composed) => {
+                result.pop();
+                result.push(composed);
+            }
+            None => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("３３３－６６６６", "3336666")]
+    #[case(" 333-6666 ", "3336666")]
+    #[case("(03)1234-5678", "0312345678")]
+    #[case("０９０（１２３４）５６７８", "09012345678")]
+    #[case("090 1234 5678", "09012345678")]
+    fn test_to_digits(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(to_digits(value), expected);
+    }
+
+    #[rstest]
+    #[case("ﾔﾏﾀﾞ ﾀﾛｳ", "ヤマダ タロウ")]
+    #[case("やまだ　はなこ", "ヤマダ　ハナコ")]
+    #[case("ﾊﾟﾋﾟﾌﾟﾍﾟﾎﾟ", "パピプペポ")]
+    #[case("ｳﾞｧｲｵﾘﾝ", "ヴァイオリン")]
+    #[case("ｶﾞｷﾞｸﾞｹﾞｺﾞｻﾞｼﾞｽﾞｾﾞｿﾞﾀﾞﾁﾞﾂﾞﾃﾞﾄﾞﾊﾞﾋﾞﾌﾞﾍﾞﾎﾞ", "ガギグゲゴザジズゼゾダヂヅデドバビブベボ")]
+    #[case("ｱﾞ", "ア゛")]
+    #[case("ｰ", "ー")]
+    #[case("カナ", "カナ")]
+    fn test_to_full_width_katakana(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(to_full_width_katakana(value), expected);
+    }
+
+    #[test]
+    fn test_to_half_width_ascii() {
+        assert_eq!(
+            to_half_width_ascii("ｓａｍｐｌｅ＠ｅｘａｍｐｌｅ．ｃｏｍ"),
+            "sample@example.com"
+        );
+    }
+
+    #[test]
+    fn test_apply_records_change() {
+        let mut changes = vec![];
+        let mut value = "333-6666".to_string();
+        apply(&mut changes, "postal_code", &mut value, to_digits);
+        apply(&mut changes, "postal_code", &mut value, to_digits);
+
+        assert_eq!(value, "3336666");
+        assert_eq!(
+            changes,
+            vec![FieldChange {
+                field: "postal_code".into(),
+                before: "333-6666".into(),
+                after: "3336666".into(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use futures::{Stream, TryStreamExt};
+use reqwest::header::{HeaderMap, LINK};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::{LectoError, Result};
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// 次に取得するページの指定方法
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PageRequest {
+    /// `page` (1始まり) と `per_page` で指定する
+    Number(u64),
+    /// レスポンスの `next_cursor` を `cursor` として渡す
+    Cursor(String),
+    /// `Link: <...>; rel="next"` のURLをそのまま使う。相対URLは取得したページのURLを基準にする
+    Url(Url),
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::Number(1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<PageRequest>,
+}
+
+impl<T> Page<T> {
+    /// レスポンスbodyとヘッダから1ページ分を組み立てる。
+    /// bodyは配列そのもの、もしくは `{"data": [...], "meta": {"next_cursor": ..., "next_page": ...}}`
+    /// の形を受け付ける。次ページの情報がなければ件数が `page_size` に達しているかで判断するが、
+    /// bodyが配列そのものの場合は `assume_page_numbers` (`page` を解釈するエンドポイント) のときだけ。
+    /// `url` は取得したページのURLで、`Link` の相対URLの基準にする
+    pub(crate) fn parse(
+        body: String,
+        headers: &HeaderMap,
+        url: &Url,
+        current: &PageRequest,
+        page_size: usize,
+        assume_page_numbers: bool,
+    ) -> Result<Self>
+    where
+        T: DeserializeOwned,
+    {
+        let value: Value = serde_json::from_str(&body).map_err(|source| LectoError::Decode {
+            source,
+            body: body.clone(),
+        })?;
+        let Some((items, hint, has_meta)) = split_body(value) else {
+            return Err(LectoError::Decode {
+                source: serde::de::Error::custom(
+                    "expected an array or an object with `data` or `items`",
+                ),
+                body,
+            });
+        };
+        let items: Vec<T> =
+            serde_json::from_value(items).map_err(|source| LectoError::Decode { source, body })?;
+
+        let next = if items.is_empty() {
+            None
+        } else if let Some(next) = next_link(headers, url) {
+            Some(PageRequest::Url(next))
+        } else if let Some(hint) = hint {
+            Some(hint)
+        } else {
+            match current {
+                PageRequest::Number(n)
+                    if items.len() >= page_size && (assume_page_numbers || has_meta) =>
+                {
+                    Some(PageRequest::Number(n + 1))
+                }
+                _ => None,
+            }
+        };
+
+        Ok(Self { items, next })
+    }
+}
+
+/// 一覧、次ページの指定、ページングの情報 (`meta`) があったか。
+/// オブジェクトに `data` も `items` もなければ `None`
+fn split_body(value: Value) -> Option<(Value, Option<PageRequest>, bool)> {
+    match value {
+        Value::Object(mut map) => {
+            let items = map.remove("data").or_else(|| map.remove("items"))?;
+            let (meta, has_meta) = match map.remove("meta") {
+                Some(Value::Object(meta)) => (meta, true),
+                _ => (map, false),
+            };
+            let hint = match (meta.get("next_cursor"), meta.get("next_page")) {
+                (Some(Value::String(cursor)), _) => Some(PageRequest::Cursor(cursor.clone())),
+                (_, Some(Value::Number(n))) => n.as_u64().map(PageRequest::Number),
+                _ => None,
+            };
+            Some((items, hint, has_meta))
+        }
+        v => Some((v, None, false)),
+    }
+}
+
+/// `Link` ヘッダから `rel="next"` のURLを取り出す。相対URLは `base` を基準に解決する
+fn next_link(headers: &HeaderMap, base: &Url) -> Option<Url> {
+    headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|link| {
+            let mut parts = link.split(';').map(str::trim);
+            let url = parts.next()?.strip_prefix('<')?.strip_suffix('>')?;
+            parts
+                .any(|p| p == r#"rel="next""# || p == "rel=next")
+                .then(|| base.join(url).ok())
+                .flatten()
+        })
+}
+
+/// ストリームを最後まで読んで `Vec` にする
+pub async fn collect_all<T>(stream: impl Stream<Item = Result<T>>) -> Result<Vec<T>> {
+    stream.try_collect().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn url() -> Url {
+        Url::parse("https://example.com/debts?page=2").unwrap()
+    }
+
+    fn headers(link: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(link) = link {
+            headers.insert(LINK, link.parse().unwrap());
+        }
+        headers
+    }
+
+    #[rstest]
+    #[case("[1, 2]", PageRequest::Number(1), None)]
+    #[case("[1, 2, 3]", PageRequest::Number(1), Some(PageRequest::Number(2)))]
+    #[case("[]", PageRequest::Number(3), None)]
+    #[case(r#"{"data": [1], "meta": {"next_cursor": "abc"}}"#, PageRequest::Number(1), Some(PageRequest::Cursor("abc".into())))]
+    #[case(
+        r#"{"items": [1], "next_page": 5}"#,
+        PageRequest::Number(4),
+        Some(PageRequest::Number(5))
+    )]
+    #[case(r#"{"data": [1, 2, 3], "meta": {"next_cursor": null}}"#, PageRequest::Cursor("abc".into()), None)]
+    fn test_parse_page(
+        #[case] body: &str,
+        #[case] current: PageRequest,
+        #[case] expected: Option<PageRequest>,
+    ) -> anyhow::Result<()> {
+        let page: Page<u64> = Page::parse(body.into(), &headers(None), &url(), &current, 3, true)?;
+        assert_eq!(page.next, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("[1, 2, 3]", None)]
+    #[case(
+        r#"{"data": [1, 2, 3], "meta": {"total": 10}}"#,
+        Some(PageRequest::Number(2))
+    )]
+    #[case(
+        r#"{"data": [1, 2, 3], "meta": {"next_page": 2}}"#,
+        Some(PageRequest::Number(2))
+    )]
+    fn test_parse_page_without_page_numbers(
+        #[case] body: &str,
+        #[case] expected: Option<PageRequest>,
+    ) -> anyhow::Result<()> {
+        let page: Page<u64> = Page::parse(
+            body.into(),
+            &headers(None),
+            &url(),
+            &PageRequest::Number(1),
+            3,
+            false,
+        )?;
+        assert_eq!(page.next, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(
+        "<https://example.com/debts?page=3>",
+        "https://example.com/debts?page=3"
+    )]
+    #[case("</debts?page=3>", "https://example.com/debts?page=3")]
+    #[case("<?page=3>", "https://example.com/debts?page=3")]
+    #[case(
+        "<https://other.example.com/debts?page=3>",
+        "https://other.example.com/debts?page=3"
+    )]
+    fn test_parse_page_link_header(
+        #[case] next: &str,
+        #[case] expected: &str,
+    ) -> anyhow::Result<()> {
+        let page: Page<u64> = Page::parse(
+            "[1]".into(),
+            &headers(Some(&format!(
+                r#"<https://example.com/debts?page=1>; rel="prev", {}; rel="next""#,
+                next
+            ))),
+            &url(),
+            &PageRequest::Number(2),
+            100,
+            false,
+        )?;
+
+        assert_eq!(page.items, vec![1]);
+        assert_eq!(page.next, Some(PageRequest::Url(Url::parse(expected)?)));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(r#"["a"]"#)]
+    #[case(r#"{"errors": ["InternalServerError"]}"#)]
+    #[case(r#"{"results": [1], "meta": {"total": 1}}"#)]
+    fn test_parse_page_decode_error(#[case] body: &str) {
+        let res: Result<Page<u64>> = Page::parse(
+            body.into(),
+            &headers(None),
+            &url(),
+            &PageRequest::default(),
+            100,
+            true,
+        );
+        assert!(matches!(res, Err(LectoError::Decode { .. })));
+    }
+}
@@ -1 +1,130 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+use crate::debt::Segment;
+
+pub mod query;
 pub mod remind;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemindGroupStatus {
+    Active,
+    Paused,
+    /// このクライアントが知らないステータス。受け取った文字列をそのまま持つ
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+/// リマインドを送る日と時刻
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemindSchedule {
+    /// 支払期日から何日後に送るか。負なら期日前
+    pub days_after_due: Vec<i64>,
+    /// 送信時刻 (業務タイムゾーン)
+    pub send_at: NaiveTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemindGroup {
+    pub id: u64,
+    pub name: String,
+    pub status: RemindGroupStatus,
+    pub remind_segments: Vec<Segment>,
+    pub schedule: RemindSchedule,
+}
+
+impl RemindGroup {
+    pub fn is_active(&self) -> bool {
+        self.status == RemindGroupStatus::Active
+    }
+
+    /// `segment` がこのグループのリマインド対象か
+    pub fn has_segment(&self, segment: &str) -> bool {
+        self.remind_segments.iter().any(|s| s.name == segment)
+    }
+}
+
+/// `POST /remind_groups` 用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemindGroupRequest {
+    pub name: String,
+    pub remind_segments: Vec<String>,
+    pub schedule: RemindSchedule,
+    /// 省略するとサーバー側のデフォルト
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<RemindGroupStatus>,
+}
+
+/// `PATCH /remind_groups/{id}` 用。`None` のフィールドは送らない
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
+pub struct RemindGroupPatchRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remind_segments: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<RemindSchedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<RemindGroupStatus>,
+}
+
+impl RemindGroupPatchRequest {
+    /// ステータスだけを変える
+    pub fn status(status: RemindGroupStatus) -> Self {
+        Self {
+            status: Some(status),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize() -> anyhow::Result<()> {
+        let group: RemindGroup = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "督促メール",
+            "status": "active",
+            "remind_segments": [{ "name": "y2021" }],
+            "schedule": { "days_after_due": [-3, 1, 7], "send_at": "10:00:00" },
+        }))?;
+
+        assert_eq!(
+            group,
+            RemindGroup {
+                id: 1,
+                name: "督促メール".into(),
+                status: RemindGroupStatus::Active,
+                remind_segments: vec![Segment {
+                    name: "y2021".into()
+                }],
+                schedule: RemindSchedule {
+                    days_after_due: vec![-3, 1, 7],
+                    send_at: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+                },
+            }
+        );
+        assert!(group.is_active());
+        assert!(group.has_segment("y2021"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_patch_request() -> anyhow::Result<()> {
+        assert_eq!(
+            serde_json::to_string(&RemindGroupPatchRequest::status(RemindGroupStatus::Paused))?,
+            r#"{"status":"paused"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<RemindGroupStatus>(r#""archived""#)?,
+            RemindGroupStatus::Unknown("archived".into())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use chrono::NaiveDate;
+
+use crate::remind_group::remind::Remind;
+use crate::validation::{FieldErrorKind, ValidationErrors};
+
+/// `Client::query_reminds` の条件。
+/// Lectoは `remind_at` を1日ずつしか受け付けないため、期間指定は1日ずつ取得して `label` ごとにまとめる。
+/// セグメントと債務者の絞り込みは取得後にクライアント側で行う
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemindQuery {
+    pub from: NaiveDate,
+    /// この日を含む
+    pub to: NaiveDate,
+    /// `None` ならクライアントの設定 (`ClientBuilder::ignore_remind_group_status`) に従う
+    pub ignore_remind_group_status: Option<bool>,
+    /// 空でなければ、いずれかのセグメントに属する債権だけを残す
+    pub remind_segments: Vec<String>,
+    /// 空でなければ、この債務者のリマインドだけを残す
+    pub debtor_ids: Vec<String>,
+    /// `None` ならクライアントの `page_size`
+    pub page_size: Option<usize>,
+}
+
+impl RemindQuery {
+    /// 1回で取得できる最大の日数。1日ずつリクエストするので、長すぎる期間は弾く
+    pub const MAX_DAYS: i64 = 92;
+
+    /// `remind_at` の1日分
+    pub fn on(remind_at: NaiveDate) -> Self {
+        Self::between(remind_at, remind_at)
+    }
+
+    /// `from` から `to` まで (両端を含む)
+    pub fn between(from: NaiveDate, to: NaiveDate) -> Self {
+        Self {
+            from,
+            to,
+            ignore_remind_group_status: None,
+            remind_segments: vec![],
+            debtor_ids: vec![],
+            page_size: None,
+        }
+    }
+
+    pub fn with_ignore_remind_group_status(mut self, ignore: bool) -> Self {
+        self.ignore_remind_group_status = Some(ignore);
+        self
+    }
+
+    pub fn with_remind_segment(mut self, segment: impl Into<String>) -> Self {
+        self.remind_segments.push(segment.into());
+        self
+    }
+
+    pub fn with_debtor_id(mut self, debtor_id: impl Into<String>) -> Self {
+        self.debtor_ids.push(debtor_id.into());
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = Some(page_size.max(1));
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.to < self.from {
+            errors.push("to", FieldErrorKind::OutOfRange, "must be on or after from");
+        } else if (self.to - self.from).num_days() >= Self::MAX_DAYS {
+            errors.push(
+                "to",
+                FieldErrorKind::OutOfRange,
+                format!("must be within {} days from from", Self::MAX_DAYS),
+            );
+        }
+        errors.into_result()
+    }
+
+    /// 取得する日付 (`from` から `to` まで)
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.from.iter_days().take_while(|d| *d <= self.to)
+    }
+
+    /// 債務者とセグメントの条件で絞り込む。債権が残らなかったリマインドは除く
+    pub(crate) fn filter(&self, mut remind: Remind) -> Option<Remind> {
+        if !self.debtor_ids.is_empty() && !self.debtor_ids.contains(&remind.debtor.debtor_id) {
+            return None;
+        }
+        if !self.remind_segments.is_empty() {
+            remind.debts.retain(|debt| {
+                debt.remind_segments
+                    .iter()
+                    .any(|s| self.remind_segments.contains(&s.name))
+            });
+        }
+        (!remind.debts.is_empty()).then_some(remind)
+    }
+}
+
+/// 日ごとのリマインドを `label` ごとにまとめる。
+/// 同じ債権が複数日に出てきたら後の日のものを残し、並びは最初に出てきた順
+pub(crate) fn merge_by_label(reminds: impl IntoIterator<Item = Remind>) -> Vec<Remind> {
+    let mut merged: Vec<Remind> = vec![];
+    for remind in reminds {
+        let Some(existing) = merged.iter_mut().find(|r| r.label == remind.label) else {
+            merged.push(remind);
+            continue;
+        };
+        existing.debtor = remind.debtor;
+        for debt in remind.debts {
+            match existing
+                .debts
+                .iter_mut()
+                .find(|d| d.debt_id == debt.debt_id)
+            {
+                Some(d) => *d = debt,
+                None => existing.debts.push(debt),
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::fixture;
+
+    #[test]
+    fn test_dates() {
+        let from = NaiveDate::from_ymd_opt(2022, 2, 27).unwrap();
+        let to = NaiveDate::from_ymd_opt(2022, 3, 1).unwrap();
+        let query = RemindQuery::between(from, to);
+
+        assert_eq!(query.dates().count(), 3);
+        assert_eq!(query.validate(), Ok(()));
+        assert!(RemindQuery::between(to, from).validate().is_err());
+
+        let from = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let last = from + chrono::Days::new(RemindQuery::MAX_DAYS as u64 - 1);
+        assert_eq!(RemindQuery::between(from, last).validate(), Ok(()));
+        let errors = RemindQuery::between(from, last.succ_opt().unwrap())
+            .validate()
+            .unwrap_err();
+        assert_eq!(errors.errors[0].kind, FieldErrorKind::OutOfRange);
+        assert!(RemindQuery::between(
+            NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2100, 1, 1).unwrap()
+        )
+        .validate()
+        .is_err());
+        assert_eq!(
+            RemindQuery::on(from).dates().collect::<Vec<_>>(),
+            vec![from]
+        );
+    }
+
+    #[test]
+    fn test_merge_by_label() -> anyhow::Result<()> {
+        let reminds = fixture::reminds();
+        let mut next_day = reminds[0].clone();
+        next_day.debts[0].debt_amount = crate::Yen::new(0);
+        next_day.debts.remove(1);
+
+        let merged = merge_by_label([reminds[0].clone(), reminds[1].clone(), next_day]);
+        assert_eq!(
+            merged.iter().map(|r| r.label.as_str()).collect::<Vec<_>>(),
+            vec![reminds[0].label.as_str(), reminds[1].label.as_str()]
+        );
+        assert_eq!(merged[0].debts.len(), reminds[0].debts.len());
+        assert_eq!(merged[0].debts[0].debt_amount, crate::Yen::new(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter() -> anyhow::Result<()> {
+        let reminds = fixture::reminds();
+        let debtor_id = reminds[0].debtor.debtor_id.clone();
+
+        let by_debtor = RemindQuery::on(NaiveDate::MIN).with_debtor_id(&debtor_id);
+        assert!(by_debtor.filter(reminds[0].clone()).is_some());
+        assert!(RemindQuery::on(NaiveDate::MIN)
+            .with_debtor_id("other")
+            .filter(reminds[0].clone())
+            .is_none());
+
+        let by_segment = RemindQuery::on(NaiveDate::MIN).with_remind_segment("AAA");
+        assert!(by_segment.filter(reminds[0].clone()).is_some());
+        assert!(RemindQuery::on(NaiveDate::MIN)
+            .with_remind_segment("ZZZ")
+            .filter(reminds[0].clone())
+            .is_none());
+        Ok(())
+    }
+}
@@ -1,11 +1,15 @@
-use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::{
     debt::Debt,
     debtor::{Debtor, DebtorResponse},
+    money::Yen,
 };
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Remind {
     pub label: String,
     pub debtor: Debtor,
@@ -18,6 +22,75 @@ pub struct RemindResponse {
     pub debtor: DebtorResponse,
     pub debts: Vec<Debt>,
 }
+impl Remind {
+    /// このリマインドに含まれる債権の残高 (債権額・手数料・遅延損害金) の合計
+    pub fn total_outstanding(&self) -> Option<Yen> {
+        self.debts
+            .iter()
+            .map(Debt::total_outstanding)
+            .try_fold(Yen::ZERO, |acc, v| acc.checked_add(v?))
+    }
+
+    /// 債務者 (`debtor_id`) ごとの残高の合計。
+    /// 同じ債務者が複数のリマインド (月違いなど) に出てきてもまとめる
+    pub fn totals_by_debtor<'a>(
+        reminds: impl IntoIterator<Item = &'a Remind>,
+    ) -> Option<BTreeMap<String, Yen>> {
+        let mut totals: BTreeMap<String, Yen> = BTreeMap::new();
+        for remind in reminds {
+            let total = totals.entry(remind.debtor.debtor_id.clone()).or_default();
+            *total = total.checked_add(remind.total_outstanding()?)?;
+        }
+        Some(totals)
+    }
+}
+
+/// 1件分のデコードに失敗したリマインド
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemindDecodeError {
+    /// 失敗した箇所。`[3].debts[0].debt_amount` のように一覧の先頭からの位置を含む
+    pub path: String,
+    /// 失敗したリマインド1件分の値
+    pub raw: Value,
+    pub message: String,
+}
+
+/// `Client::get_reminds_lenient` の結果。デコードできたものとできなかったもの
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LenientReminds {
+    pub reminds: Vec<Remind>,
+    pub errors: Vec<RemindDecodeError>,
+}
+
+impl LenientReminds {
+    /// 全件デコードできた
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl RemindDecodeError {
+    pub(crate) fn new(index: usize, field: &str, raw: Value, message: String) -> Self {
+        let path = match field {
+            "" | "." => format!("[{}]", index),
+            field => format!("[{}].{}", index, field),
+        };
+        Self { path, raw, message }
+    }
+}
+
+/// 一覧の `index` 番目のリマインドをデコードする
+pub(crate) fn decode_remind(
+    index: usize,
+    raw: Value,
+) -> std::result::Result<RemindResponse, RemindDecodeError> {
+    serde_path_to_error::deserialize(&raw).map_err(|e| {
+        let field = e.path().to_string();
+        let message = e.into_inner().to_string();
+        RemindDecodeError::new(index, &field, raw.clone(), message)
+    })
+}
+
 impl From<RemindResponse> for Remind {
     fn from(item: RemindResponse) -> Self {
         Self {
@@ -38,15 +111,14 @@ mod tests {
         debtor::{
             DebtorAddressResponse, DebtorBasicInformation, DebtorEmail, DebtorPhoneNumber, Gender,
         },
-        DebtStatus, DebtStatusVariable,
+        fixture, DebtStatus, DebtStatusVariable,
     };
 
     use super::*;
 
     #[test]
     fn test_deserialize() -> anyhow::Result<()> {
-        let json = std::fs::read_to_string("test-data/lecto-remind-groups-reminds.json")
-            .map_err(|e| dbg!(e))?;
+        let json = fixture::remind_responses_json();
 
         let data: Vec<RemindResponse> = serde_json::from_str(&json).map_err(|e| dbg!(e))?;
 
@@ -81,9 +153,9 @@ mod tests {
                         debt_id: "test external id5".into(),
                         debtor_id: "test external id3".into(),
                         dealt_at: "2022-03-12T00:31:57+09:00".parse().unwrap(),
-                        debt_amount: 10000,
-                        debt_fee: Some(100),
-                        debt_delinquency_charge: Some(500),
+                        debt_amount: Yen::new(10000),
+                        debt_fee: Some(Yen::new(100)),
+                        debt_delinquency_charge: Some(Yen::new(500)),
                         repayment_due_at: "2022-03-17T00:00:00+09:00".parse().unwrap(),
                         custom_fields:  [
                             ("item_name".into(), "iPhoneSE 12".into()),
@@ -105,9 +177,9 @@ mod tests {
                         debt_id: "test external id6".into(),
                         debtor_id: "test external id3".into(),
                         dealt_at: "2022-03-12T00:31:57+09:00".parse().unwrap(),
-                        debt_amount: 10000,
-                        debt_fee: Some(100),
-                        debt_delinquency_charge: Some(500),
+                        debt_amount: Yen::new(10000),
+                        debt_fee: Some(Yen::new(100)),
+                        debt_delinquency_charge: Some(Yen::new(500)),
                         repayment_due_at: "2022-03-18T00:00:00+09:00".parse().unwrap(),
                         custom_fields:  [
                             ("item_name".into(), "iPhoneSE 12".into()),
@@ -129,4 +201,33 @@ mod tests {
         });
         Ok(())
     }
+
+    #[test]
+    fn test_decode_remind_error_path() -> anyhow::Result<()> {
+        let json = fixture::remind_responses_json();
+        let mut items: Vec<Value> = serde_json::from_str(&json)?;
+        items[0]["debts"][1]["debt_amount"] = "10000円".into();
+
+        assert!(decode_remind(0, items[1].clone()).is_ok());
+        assert_matches!(decode_remind(7, items[0].clone()), Err(e) => {
+            assert_eq!(e.path, "[7].debts[1].debt_amount");
+            assert_eq!(e.raw, items[0]);
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_totals() -> anyhow::Result<()> {
+        let reminds = fixture::reminds();
+
+        assert_eq!(reminds[0].total_outstanding(), Some(Yen::new(21200)));
+
+        let mut next_month = reminds[0].clone();
+        next_month.label = "test external id3---2022-04".into();
+        next_month.debts.truncate(1);
+        let totals =
+            Remind::totals_by_debtor(&[reminds[0].clone(), next_month]).expect("no overflow");
+        assert_eq!(totals.get("test external id3"), Some(&Yen::new(31800)));
+        Ok(())
+    }
 }
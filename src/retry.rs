@@ -0,0 +1,397 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use reqwest::{Response, StatusCode};
+
+use crate::error::Result;
+use crate::util::parse_retry_after;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    /// バックオフ時間をそのまま使う
+    None,
+    /// 0〜バックオフ時間の一様乱数
+    Full,
+    /// バックオフ時間の半分 + 0〜半分の一様乱数
+    Equal,
+}
+
+/// リトライの回数・間隔・対象ステータスを決めるポリシー
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: Jitter,
+    max_elapsed: Option<Duration>,
+    retry_statuses: Vec<StatusCode>,
+    retry_transport_errors: bool,
+    respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: Jitter::Full,
+            max_elapsed: None,
+            retry_statuses: vec![
+                StatusCode::REQUEST_TIMEOUT,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_transport_errors: true,
+            respect_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// リトライしない
+    pub fn none() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    /// 固定間隔でリトライする
+    pub fn fixed(max_attempts: usize, delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: delay,
+            max_backoff: delay,
+            multiplier: 1.0,
+            jitter: Jitter::None,
+            ..Self::default()
+        }
+    }
+
+    /// 初回を含めた最大試行回数
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// 初回リクエストからの経過時間の上限。次の待ち時間でこれを超える場合はリトライしない
+    pub fn with_max_elapsed(mut self, max_elapsed: Duration) -> Self {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+
+    /// リトライ対象のステータスを置き換える。2xxは指定してもリトライしない
+    pub fn with_retry_statuses(mut self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.retry_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    pub fn with_retry_transport_errors(mut self, retry: bool) -> Self {
+        self.retry_transport_errors = retry;
+        self
+    }
+
+    /// `Retry-After` に従うか。`max_backoff` より長く待つよう指示されたらリトライせずに返す
+    /// (429なら `LectoError::TooManyRequests` の `retry_after` で受け取れる)
+    pub fn with_respect_retry_after(mut self, respect: bool) -> Self {
+        self.respect_retry_after = respect;
+        self
+    }
+
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    pub fn is_retryable_status(&self, status: StatusCode) -> bool {
+        !status.is_success() && self.retry_statuses.contains(&status)
+    }
+
+    /// `attempt` 回目 (1始まり) の失敗後に待つ時間
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let exp = self
+            .multiplier
+            .powi(attempt.saturating_sub(1).min(i32::MAX as usize) as i32);
+        let exp = exp.max(0.0);
+        // 回数が多いと `Duration` に収まらなくなるので、掛ける前に上限と比べる
+        let base = if self.initial_backoff.is_zero() {
+            Duration::ZERO
+        } else if self.initial_backoff.as_secs_f64() * exp >= self.max_backoff.as_secs_f64() {
+            self.max_backoff
+        } else {
+            self.initial_backoff.mul_f64(exp)
+        };
+
+        match self.jitter {
+            Jitter::None => base,
+            Jitter::Full => base.mul_f64(fastrand::f64()),
+            Jitter::Equal => base / 2 + (base / 2).mul_f64(fastrand::f64()),
+        }
+    }
+
+    /// ポリシーに従って `send` を繰り返す。
+    /// 最終的に得られたレスポンスはステータスに関わらず `Ok` で返す
+    pub async fn execute<F, R>(&self, send: F) -> Result<Response>
+    where
+        R: Future<Output = reqwest::Result<Response>>,
+        F: Fn() -> R,
+    {
+        self.execute_with_level(send, log::Level::Error).await
+    }
+
+    pub(crate) async fn execute_with_level<F, R>(
+        &self,
+        send: F,
+        level: log::Level,
+    ) -> Result<Response>
+    where
+        R: Future<Output = reqwest::Result<Response>>,
+        F: Fn() -> R,
+    {
+        let started = Instant::now();
+        let mut attempts = 1;
+        loop {
+            let res = send().await;
+            if attempts >= self.max_attempts {
+                return Ok(res?);
+            }
+
+            let delay = match &res {
+                Ok(x) if self.is_retryable_status(x.status()) => {
+                    log::log!(
+                        level,
+                        "👻 Lecto returned {}! will retry attempts: {}, Response: {:?}",
+                        x.status(),
+                        attempts,
+                        x,
+                    );
+                    match parse_retry_after(x.headers()).filter(|_| self.respect_retry_after) {
+                        Some(retry_after) if retry_after > self.max_backoff => {
+                            log::log!(
+                                level,
+                                "👻 Retry-After {:?} exceeds max backoff {:?}, giving up",
+                                retry_after,
+                                self.max_backoff
+                            );
+                            return Ok(res?);
+                        }
+                        Some(retry_after) => retry_after,
+                        None => self.backoff(attempts),
+                    }
+                }
+                Err(e) if self.retry_transport_errors => {
+                    log::log!(
+                        level,
+                        "👻 Reqwest Error! will retry attempts: {}, Error: {:?}",
+                        attempts,
+                        e
+                    );
+                    self.backoff(attempts)
+                }
+                _ => return Ok(res?),
+            };
+
+            if let Some(max_elapsed) = self.max_elapsed {
+                if started.elapsed() + delay > max_elapsed {
+                    return Ok(res?);
+                }
+            }
+
+            attempts += 1;
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .with_jitter(Jitter::None)
+            .with_initial_backoff(Duration::from_millis(10))
+    }
+
+    #[rstest]
+    #[case(1, Duration::from_millis(100))]
+    #[case(2, Duration::from_millis(200))]
+    #[case(3, Duration::from_millis(400))]
+    #[case(10, Duration::from_secs(1))]
+    fn test_backoff(#[case] attempt: usize, #[case] expected: Duration) {
+        let policy = RetryPolicy::default()
+            .with_jitter(Jitter::None)
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_secs(1));
+
+        assert_eq!(policy.backoff(attempt), expected);
+    }
+
+    #[test]
+    fn test_backoff_does_not_overflow() {
+        let policy = RetryPolicy::default()
+            .with_jitter(Jitter::None)
+            .with_max_attempts(1000);
+
+        assert_eq!(policy.backoff(1000), Duration::from_secs(30));
+        assert_eq!(
+            RetryPolicy::default()
+                .with_jitter(Jitter::None)
+                .with_max_backoff(Duration::MAX)
+                .backoff(1000),
+            Duration::MAX
+        );
+    }
+
+    #[test]
+    fn test_backoff_with_jitter() {
+        let policy = RetryPolicy::default()
+            .with_jitter(Jitter::Equal)
+            .with_initial_backoff(Duration::from_millis(100));
+
+        for _ in 0..100 {
+            let d = policy.backoff(1);
+            assert!(d >= Duration::from_millis(50) && d <= Duration::from_millis(100));
+        }
+    }
+
+    #[rstest]
+    #[case(StatusCode::OK, false)]
+    #[case(StatusCode::CREATED, false)]
+    #[case(StatusCode::NO_CONTENT, false)]
+    #[case(StatusCode::UNAUTHORIZED, false)]
+    #[case(StatusCode::NOT_FOUND, false)]
+    #[case(StatusCode::UNPROCESSABLE_ENTITY, false)]
+    #[case(StatusCode::TOO_MANY_REQUESTS, true)]
+    #[case(StatusCode::BAD_GATEWAY, true)]
+    #[case(StatusCode::SERVICE_UNAVAILABLE, true)]
+    #[case(StatusCode::GATEWAY_TIMEOUT, true)]
+    fn test_is_retryable_status(#[case] status: StatusCode, #[case] expected: bool) {
+        assert_eq!(RetryPolicy::default().is_retryable_status(status), expected);
+    }
+
+    #[test]
+    fn test_success_is_never_retryable() {
+        let policy = RetryPolicy::default().with_retry_statuses([StatusCode::CREATED]);
+        assert!(!policy.is_retryable_status(StatusCode::CREATED));
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_success() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server.mock("GET", "/").with_status(503).expect(2).create();
+        let ok = server.mock("GET", "/").with_status(201).create();
+
+        let client = reqwest::Client::new();
+        let res = policy().execute(|| client.get(server.url()).send()).await?;
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        unavailable.assert();
+        ok.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_not_retry_not_found() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("GET", "/").with_status(404).expect(1).create();
+
+        let client = reqwest::Client::new();
+        let res = policy().execute(|| client.get(server.url()).send()).await?;
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_honor_retry_after() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let limited = server
+            .mock("GET", "/")
+            .with_status(429)
+            .with_header("retry-after", "1")
+            .expect(1)
+            .create();
+        let ok = server.mock("GET", "/").with_status(200).create();
+
+        let client = reqwest::Client::new();
+        let started = Instant::now();
+        let res = policy().execute(|| client.get(server.url()).send()).await?;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        limited.assert();
+        ok.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_give_up_on_long_retry_after() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .with_status(429)
+            .with_header("retry-after", "86400")
+            .expect(1)
+            .create();
+
+        let client = reqwest::Client::new();
+        let started = Instant::now();
+        let res = policy()
+            .with_max_attempts(5)
+            .execute(|| client.get(server.url()).send())
+            .await?;
+
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stop_at_max_elapsed() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .with_status(503)
+            .with_header("retry-after", "60")
+            .expect(1)
+            .create();
+
+        let client = reqwest::Client::new();
+        let res = policy()
+            .with_max_attempts(5)
+            .with_max_elapsed(Duration::from_secs(5))
+            .execute(|| client.get(server.url()).send())
+            .await?;
+
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        mock.assert();
+        Ok(())
+    }
+}
@@ -0,0 +1,532 @@
+//! 毎日決まった時刻に、指定したリマインドグループのリマインドを取得して渡す。
+//!
+//! 時刻と日付はクライアントの業務タイムゾーンで扱う。カレンダー上の休みの日は動かず、
+//! 休みの日や止まっていた間の分は次の営業日にまとめて取得する (`remind_at` ごとに1回ずつ渡す)。
+//! 最後に渡し終えた日をグループごとに `StateStore` に保存するので、途中で失敗したグループは
+//! 次にその翌日の分からやり直す。失敗したグループがあっても他のグループは先に進む
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Days, FixedOffset, NaiveDate, NaiveTime, TimeZone};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::sync::mpsc;
+
+use crate::client::Client;
+use crate::remind_group::query::RemindQuery;
+use crate::remind_group::remind::Remind;
+use crate::tz;
+use crate::LectoError;
+
+pub mod calendar;
+
+pub use calendar::{BusinessCalendar, JapaneseCalendar};
+
+/// 1グループ・1日分のリマインド
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemindBatch {
+    pub remind_group_id: u64,
+    pub remind_at: NaiveDate,
+    pub reminds: Vec<Remind>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError {
+    #[error(transparent)]
+    Lecto(#[from] LectoError),
+    #[error("failed to access scheduler state: {0}")]
+    State(#[from] std::io::Error),
+    #[error(
+        "failed to deliver reminds for {remind_at} (remind group {remind_group_id}): {message}"
+    )]
+    Delivery {
+        remind_group_id: u64,
+        remind_at: NaiveDate,
+        message: String,
+    },
+}
+
+/// `run_due` の結果
+#[derive(Debug, Default)]
+pub struct RunReport {
+    /// 渡し終えた `(remind_group_id, remind_at)`
+    pub delivered: Vec<(u64, NaiveDate)>,
+    /// 失敗したグループと、そのグループで最初に起きたエラー。残りの日は次回に回す
+    pub failures: Vec<(u64, SchedulerError)>,
+}
+
+impl RunReport {
+    pub fn is_all_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// リマインドグループごとに、最後に渡し終えた `remind_at` の保存先
+pub trait StateStore: Send + Sync {
+    fn load(&self, remind_group_id: u64) -> std::io::Result<Option<NaiveDate>>;
+    fn save(&self, remind_group_id: u64, date: NaiveDate) -> std::io::Result<()>;
+}
+
+/// `<remind_group_id> YYYY-MM-DD` をグループごとに1行書いたファイルに保存する
+#[derive(Debug, Clone)]
+pub struct FileStateStore {
+    path: PathBuf,
+}
+
+impl FileStateStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn read_all(&self) -> std::io::Result<BTreeMap<u64, NaiveDate>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e),
+        };
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.trim()
+                    .split_once(' ')
+                    .and_then(|(id, date)| Some((id.parse().ok()?, date.trim().parse().ok()?)))
+                    .ok_or_else(|| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("invalid state line: {:?}", line),
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+impl StateStore for FileStateStore {
+    fn load(&self, remind_group_id: u64) -> std::io::Result<Option<NaiveDate>> {
+        Ok(self.read_all()?.get(&remind_group_id).copied())
+    }
+
+    /// 書きかけのファイルを残さないよう、一時ファイルに書いてから置き換える
+    fn save(&self, remind_group_id: u64, date: NaiveDate) -> std::io::Result<()> {
+        let mut state = self.read_all()?;
+        state.insert(remind_group_id, date);
+        let text: String = state
+            .iter()
+            .map(|(id, date)| format!("{} {}\n", id, date))
+            .collect();
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(tmp, &self.path)
+    }
+}
+
+/// プロセス内だけで覚える。再起動すると当日分から始まる
+#[derive(Debug, Default)]
+pub struct MemoryStateStore {
+    last: Mutex<HashMap<u64, NaiveDate>>,
+}
+
+impl MemoryStateStore {
+    /// `last` はグループごとの最後に渡し終えた日
+    pub fn new(last: impl IntoIterator<Item = (u64, NaiveDate)>) -> Self {
+        Self {
+            last: Mutex::new(last.into_iter().collect()),
+        }
+    }
+}
+
+impl StateStore for MemoryStateStore {
+    fn load(&self, remind_group_id: u64) -> std::io::Result<Option<NaiveDate>> {
+        Ok(self.last.lock().unwrap().get(&remind_group_id).copied())
+    }
+
+    fn save(&self, remind_group_id: u64, date: NaiveDate) -> std::io::Result<()> {
+        self.last.lock().unwrap().insert(remind_group_id, date);
+        Ok(())
+    }
+}
+
+type DeliverFn = Box<dyn Fn(RemindBatch) -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+pub struct RemindScheduler {
+    client: Client,
+    remind_group_ids: Vec<u64>,
+    run_at: NaiveTime,
+    calendar: Box<dyn BusinessCalendar>,
+    store: Box<dyn StateStore>,
+    deliver: DeliverFn,
+    max_backfill_days: u64,
+    retry_interval: Duration,
+}
+
+impl RemindScheduler {
+    /// 営業日の `run_at` (業務タイムゾーン) に、グループ・日付ごとに `on_reminds` を呼ぶ
+    pub fn new<F, Fut, E>(
+        client: Client,
+        remind_group_ids: impl IntoIterator<Item = u64>,
+        run_at: NaiveTime,
+        on_reminds: F,
+    ) -> Self
+    where
+        F: Fn(RemindBatch) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: fmt::Display,
+    {
+        Self {
+            client,
+            remind_group_ids: remind_group_ids.into_iter().collect(),
+            run_at,
+            calendar: Box::new(JapaneseCalendar::new()),
+            store: Box::new(MemoryStateStore::default()),
+            deliver: Box::new(move |batch| {
+                on_reminds(batch)
+                    .map(|res| res.map_err(|e| e.to_string()))
+                    .boxed()
+            }),
+            max_backfill_days: 31,
+            retry_interval: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// `new` のコールバックの代わりに `sender` に送る。受信側が閉じていたら失敗にする
+    pub fn with_sender(
+        client: Client,
+        remind_group_ids: impl IntoIterator<Item = u64>,
+        run_at: NaiveTime,
+        sender: mpsc::Sender<RemindBatch>,
+    ) -> Self {
+        Self::new(client, remind_group_ids, run_at, move |batch| {
+            let sender = sender.clone();
+            async move { sender.send(batch).await }
+        })
+    }
+
+    /// 休みの日。デフォルトは `JapaneseCalendar::new()`
+    pub fn with_calendar(mut self, calendar: impl BusinessCalendar + 'static) -> Self {
+        self.calendar = Box::new(calendar);
+        self
+    }
+
+    /// グループごとの最後に渡し終えた日の保存先。デフォルトはメモリ上のみ
+    pub fn with_state_store(mut self, store: impl StateStore + 'static) -> Self {
+        self.store = Box::new(store);
+        self
+    }
+
+    /// 長く止まっていた場合に、今日から何日前まで遡って取得するか。デフォルトは31日
+    pub fn with_max_backfill_days(mut self, days: u64) -> Self {
+        self.max_backfill_days = days;
+        self
+    }
+
+    /// 失敗したときに次に試すまでの間隔。デフォルトは5分
+    pub fn with_retry_interval(mut self, interval: Duration) -> Self {
+        self.retry_interval = interval;
+        self
+    }
+
+    /// 止めるまで動き続ける。失敗はログに出して `retry_interval` 後にやり直す
+    pub async fn run(&self) {
+        let time_zone = self.client.business_time_zone();
+        loop {
+            let report = self.run_due(tz::now(&time_zone)).await;
+            for (remind_group_id, e) in &report.failures {
+                log::error!(
+                    "remind scheduler failed (remind group {}): {}",
+                    remind_group_id,
+                    e
+                );
+            }
+            let wait = if report.is_all_success() {
+                let now = tz::now(&time_zone);
+                self.next_run(now)
+                    .and_then(|next| (next - now).to_std().ok())
+                    .unwrap_or(Duration::from_secs(24 * 60 * 60))
+            } else {
+                self.retry_interval
+            };
+            // スリープ中に時計がずれても遅れすぎないよう、1時間ごとに起きて確かめる
+            tokio::time::sleep(wait.min(Duration::from_secs(60 * 60))).await;
+        }
+    }
+
+    /// `now` が営業日の `run_at` を過ぎていれば、グループごとにまだ渡していない日の分を取得して渡す。
+    /// あるグループで失敗したら、そのグループの残りの日は次回に回して次のグループに進む
+    pub async fn run_due(&self, now: DateTime<FixedOffset>) -> RunReport {
+        let now = now.with_timezone(&self.client.business_time_zone());
+        let today = now.date_naive();
+        let mut report = RunReport::default();
+        if !self.calendar.is_business_day(today) || now.time() < self.run_at {
+            return report;
+        }
+
+        for &remind_group_id in &self.remind_group_ids {
+            if let Err(e) = self.run_group(remind_group_id, today, &mut report).await {
+                report.failures.push((remind_group_id, e));
+            }
+        }
+        report
+    }
+
+    async fn run_group(
+        &self,
+        remind_group_id: u64,
+        today: NaiveDate,
+        report: &mut RunReport,
+    ) -> Result<(), SchedulerError> {
+        let last = self.store.load(remind_group_id)?;
+        for date in self.pending_dates(last, today) {
+            let reminds = self
+                .client
+                .query_reminds(remind_group_id, &RemindQuery::on(date))
+                .await?;
+            log::info!(
+                "delivering {} reminds for {} (remind group {})",
+                reminds.len(),
+                date,
+                remind_group_id
+            );
+            (self.deliver)(RemindBatch {
+                remind_group_id,
+                remind_at: date,
+                reminds,
+            })
+            .await
+            .map_err(|message| SchedulerError::Delivery {
+                remind_group_id,
+                remind_at: date,
+                message,
+            })?;
+            self.store.save(remind_group_id, date)?;
+            report.delivered.push((remind_group_id, date));
+        }
+        Ok(())
+    }
+
+    /// `now` より後で、次に動く日時。1年先まで営業日がなければ `None`
+    pub fn next_run(&self, now: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        let time_zone = self.client.business_time_zone();
+        let now = now.with_timezone(&time_zone);
+        now.date_naive()
+            .iter_days()
+            .take(366)
+            .filter(|date| self.calendar.is_business_day(*date))
+            .filter_map(|date| {
+                time_zone
+                    .from_local_datetime(&date.and_time(self.run_at))
+                    .single()
+            })
+            .find(|next| *next > now)
+    }
+
+    /// `last` の翌日から `today` まで。初回は `today` だけ
+    fn pending_dates(&self, last: Option<NaiveDate>, today: NaiveDate) -> Vec<NaiveDate> {
+        let Some(mut from) = last.map_or(Some(today), |last| last.succ_opt()) else {
+            return vec![];
+        };
+        let earliest = today
+            .checked_sub_days(Days::new(self.max_backfill_days))
+            .unwrap_or(NaiveDate::MIN);
+        if from < earliest {
+            log::warn!(
+                "skipping reminds from {} to {}: more than {} days behind",
+                from,
+                earliest.pred_opt().unwrap_or(earliest),
+                self.max_backfill_days
+            );
+            from = earliest;
+        }
+        from.iter_days().take_while(|d| *d <= today).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use mockito::Matcher;
+    use pretty_assertions::assert_eq;
+
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::fixture::{self, test_client};
+    use crate::tz::JST;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    fn at(month: u32, day: u32, hour: u32) -> DateTime<FixedOffset> {
+        JST.with_ymd_and_hms(2026, month, day, hour, 0, 0).unwrap()
+    }
+
+    fn scheduler(client: Client, sender: mpsc::Sender<RemindBatch>) -> RemindScheduler {
+        RemindScheduler::with_sender(
+            client,
+            [1],
+            NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            sender,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_run_due_backfills() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/remind_groups/1/reminds")
+            .match_query(Matcher::Regex("remind_at=2026-10-1[6-9]".into()))
+            .with_status(200)
+            .with_body(fixture::remind_responses_json())
+            .expect(4)
+            .create();
+        let (tx, mut rx) = mpsc::channel(10);
+        // 木曜まで渡し終えている
+        let scheduler = scheduler(test_client("apikey", server.url()), tx)
+            .with_state_store(MemoryStateStore::new([(1, date(10, 15))]));
+
+        // 土曜と月曜の始業前は動かない
+        assert_eq!(scheduler.run_due(at(10, 17, 10)).await.delivered, vec![]);
+        assert_eq!(scheduler.run_due(at(10, 19, 8)).await.delivered, vec![]);
+
+        let dates = vec![date(10, 16), date(10, 17), date(10, 18), date(10, 19)];
+        let report = scheduler.run_due(at(10, 19, 10)).await;
+        assert!(report.is_all_success());
+        assert_eq!(
+            report.delivered,
+            dates.iter().map(|d| (1, *d)).collect::<Vec<_>>()
+        );
+        assert_eq!(scheduler.store.load(1)?, Some(date(10, 19)));
+        for d in dates {
+            let batch = rx.try_recv()?;
+            assert_eq!((batch.remind_group_id, batch.remind_at), (1, d));
+            assert_eq!(batch.reminds.len(), 2);
+        }
+
+        assert_eq!(scheduler.run_due(at(10, 19, 11)).await.delivered, vec![]);
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_due_keeps_failed_group_behind() -> anyhow::Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock(
+                "GET",
+                Matcher::Regex(r"^/remind_groups/[12]/reminds".into()),
+            )
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body("[]")
+            .create();
+        let down = Arc::new(AtomicBool::new(true));
+        let scheduler = RemindScheduler::new(
+            test_client("apikey", server.url()),
+            [1, 2],
+            NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            {
+                let down = down.clone();
+                move |batch: RemindBatch| {
+                    let fail = batch.remind_group_id == 1
+                        && batch.remind_at == date(10, 17)
+                        && down.load(Ordering::SeqCst);
+                    async move {
+                        if fail {
+                            return Err("mail server is down");
+                        }
+                        Ok(())
+                    }
+                }
+            },
+        )
+        .with_state_store(MemoryStateStore::new([
+            (1, date(10, 15)),
+            (2, date(10, 16)),
+        ]));
+
+        // グループ1は10/17で止まるが、グループ2は今日まで進む
+        let report = scheduler.run_due(at(10, 19, 10)).await;
+        assert_eq!(
+            report.delivered,
+            vec![
+                (1, date(10, 16)),
+                (2, date(10, 17)),
+                (2, date(10, 18)),
+                (2, date(10, 19)),
+            ]
+        );
+        assert_matches!(
+            report.failures.as_slice(),
+            [(1, SchedulerError::Delivery { remind_at, .. })] if *remind_at == date(10, 17)
+        );
+        assert_eq!(scheduler.store.load(1)?, Some(date(10, 16)));
+        assert_eq!(scheduler.store.load(2)?, Some(date(10, 19)));
+
+        // 次はグループ1の残りだけ
+        down.store(false, Ordering::SeqCst);
+        let report = scheduler.run_due(at(10, 19, 10)).await;
+        assert!(report.is_all_success());
+        assert_eq!(
+            report.delivered,
+            vec![(1, date(10, 17)), (1, date(10, 18)), (1, date(10, 19))]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_run() {
+        let (tx, _rx) = mpsc::channel(1);
+        let scheduler = scheduler(test_client("apikey", "http://localhost".into()), tx);
+
+        assert_eq!(scheduler.next_run(at(10, 16, 8)), Some(at(10, 16, 9)));
+        assert_eq!(scheduler.next_run(at(10, 16, 9)), Some(at(10, 19, 9)));
+        // 11/3 は文化の日
+        assert_eq!(scheduler.next_run(at(11, 2, 10)), Some(at(11, 4, 9)));
+    }
+
+    #[test]
+    fn test_pending_dates() {
+        let (tx, _rx) = mpsc::channel(1);
+        let scheduler = scheduler(test_client("apikey", "http://localhost".into()), tx)
+            .with_max_backfill_days(2);
+
+        assert_eq!(
+            scheduler.pending_dates(None, date(10, 19)),
+            vec![date(10, 19)]
+        );
+        assert_eq!(
+            scheduler.pending_dates(Some(date(10, 19)), date(10, 19)),
+            vec![]
+        );
+        assert_eq!(
+            scheduler.pending_dates(Some(date(9, 1)), date(10, 19)),
+            vec![date(10, 17), date(10, 18), date(10, 19)]
+        );
+    }
+
+    #[test]
+    fn test_file_state_store() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("lecto-scheduler-{}", std::process::id()));
+        let store = FileStateStore::new(&path);
+        assert_eq!(store.load(1)?, None);
+
+        store.save(2, date(10, 16))?;
+        store.save(1, date(10, 16))?;
+        store.save(2, date(10, 19))?;
+        assert_eq!(store.load(1)?, Some(date(10, 16)));
+        assert_eq!(store.load(2)?, Some(date(10, 19)));
+        assert_eq!(store.load(3)?, None);
+        assert_eq!(fs::read_to_string(&path)?, "1 2026-10-16\n2 2026-10-19\n");
+
+        fs::write(&path, "2026-10-19\n")?;
+        assert_matches!(store.load(1), Err(e) if e.kind() == std::io::ErrorKind::InvalidData);
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
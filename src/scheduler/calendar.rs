@@ -0,0 +1,236 @@
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use itertools::Itertools;
+
+/// リマインドを取りに行く日かどうか
+pub trait BusinessCalendar: Send + Sync {
+    fn is_business_day(&self, date: NaiveDate) -> bool;
+}
+
+/// 土日と日本の祝日 (振替休日・国民の休日を含む) を休みにするカレンダー。
+/// 祝日は2007年以降の祝日法 (2019〜2021年の特例を含む) で計算する
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JapaneseCalendar {
+    closed_weekdays: Vec<Weekday>,
+    holidays: BTreeSet<NaiveDate>,
+    business_days: BTreeSet<NaiveDate>,
+}
+
+impl Default for JapaneseCalendar {
+    fn default() -> Self {
+        Self {
+            closed_weekdays: vec![Weekday::Sat, Weekday::Sun],
+            holidays: BTreeSet::new(),
+            business_days: BTreeSet::new(),
+        }
+    }
+}
+
+impl JapaneseCalendar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 休みにする曜日。デフォルトは土日
+    pub fn with_closed_weekdays(mut self, weekdays: impl IntoIterator<Item = Weekday>) -> Self {
+        self.closed_weekdays = weekdays.into_iter().collect();
+        self
+    }
+
+    /// 祝日以外の休み (年末年始など)
+    pub fn with_holiday(mut self, date: NaiveDate) -> Self {
+        self.business_days.remove(&date);
+        self.holidays.insert(date);
+        self
+    }
+
+    /// 曜日や祝日に関係なく営業する日
+    pub fn with_business_day(mut self, date: NaiveDate) -> Self {
+        self.holidays.remove(&date);
+        self.business_days.insert(date);
+        self
+    }
+
+    pub fn is_public_holiday(date: NaiveDate) -> bool {
+        Self::public_holidays(date.year()).contains(&date)
+    }
+
+    /// `year` 年の祝日 (振替休日・国民の休日を含む)
+    pub fn public_holidays(year: i32) -> BTreeSet<NaiveDate> {
+        let base = national_holidays(year);
+        let mut holidays = base.clone();
+
+        // 前後が祝日の平日は国民の休日
+        for (prev, next) in base.iter().tuple_windows() {
+            let Some(between) = succ(*prev) else { continue };
+            if succ(between) == Some(*next) && between.weekday() != Weekday::Sun {
+                holidays.insert(between);
+            }
+        }
+        // 日曜の祝日は、その後の最初の祝日でない日が振替休日
+        for date in base.iter().filter(|d| d.weekday() == Weekday::Sun) {
+            let mut substitute = succ(*date);
+            while let Some(d) = substitute.filter(|d| holidays.contains(d)) {
+                substitute = succ(d);
+            }
+            holidays.extend(substitute);
+        }
+        holidays
+    }
+}
+
+impl BusinessCalendar for JapaneseCalendar {
+    fn is_business_day(&self, date: NaiveDate) -> bool {
+        if self.business_days.contains(&date) {
+            return true;
+        }
+        !self.closed_weekdays.contains(&date.weekday())
+            && !self.holidays.contains(&date)
+            && !Self::is_public_holiday(date)
+    }
+}
+
+fn succ(date: NaiveDate) -> Option<NaiveDate> {
+    date.checked_add_days(Days::new(1))
+}
+
+/// 「国民の祝日」そのもの (振替休日・国民の休日を除く)
+fn national_holidays(year: i32) -> BTreeSet<NaiveDate> {
+    let ymd = |month, day| NaiveDate::from_ymd_opt(year, month, day);
+    let monday = |month, n| NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Mon, n);
+
+    let (marine_day, sports_day, mountain_day) = match year {
+        2020 => (ymd(7, 23), ymd(7, 24), ymd(8, 10)),
+        2021 => (ymd(7, 22), ymd(7, 23), ymd(8, 8)),
+        _ => (monday(7, 3), monday(10, 2), ymd(8, 11)),
+    };
+    let emperors_birthday = match year {
+        ..=2018 => ymd(12, 23),
+        2019 => None,
+        _ => ymd(2, 23),
+    };
+    let mut holidays = vec![
+        ymd(1, 1),
+        monday(1, 2),
+        ymd(2, 11),
+        emperors_birthday,
+        ymd(3, equinox_day(year, 20.8431)),
+        ymd(4, 29),
+        ymd(5, 3),
+        ymd(5, 4),
+        ymd(5, 5),
+        marine_day,
+        (year >= 2016).then_some(mountain_day).flatten(),
+        monday(9, 3),
+        ymd(9, equinox_day(year, 23.2488)),
+        sports_day,
+        ymd(11, 3),
+        ymd(11, 23),
+    ];
+    if year == 2019 {
+        // 即位の日・即位礼正殿の儀
+        holidays.extend([ymd(5, 1), ymd(10, 22)]);
+    }
+    holidays.into_iter().flatten().collect()
+}
+
+/// 春分日・秋分日の近似式 (1980〜2099年)
+fn equinox_day(year: i32, base: f64) -> u32 {
+    let elapsed = (year - 1980) as f64;
+    (base + 0.242194 * elapsed - (elapsed / 4.0).floor()).floor() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn dates(year: i32, md: &[(u32, u32)]) -> BTreeSet<NaiveDate> {
+        md.iter()
+            .map(|(m, d)| NaiveDate::from_ymd_opt(year, *m, *d).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_public_holidays() {
+        assert_eq!(
+            JapaneseCalendar::public_holidays(2026),
+            dates(
+                2026,
+                &[
+                    (1, 1),
+                    (1, 12),
+                    (2, 11),
+                    (2, 23),
+                    (3, 20),
+                    (4, 29),
+                    (5, 3),
+                    (5, 4),
+                    (5, 5),
+                    (5, 6),
+                    (7, 20),
+                    (8, 11),
+                    (9, 21),
+                    (9, 22),
+                    (9, 23),
+                    (10, 12),
+                    (11, 3),
+                    (11, 23),
+                ]
+            )
+        );
+        assert_eq!(
+            JapaneseCalendar::public_holidays(2025),
+            dates(
+                2025,
+                &[
+                    (1, 1),
+                    (1, 13),
+                    (2, 11),
+                    (2, 23),
+                    (2, 24),
+                    (3, 20),
+                    (4, 29),
+                    (5, 3),
+                    (5, 4),
+                    (5, 5),
+                    (5, 6),
+                    (7, 21),
+                    (8, 11),
+                    (9, 15),
+                    (9, 23),
+                    (10, 13),
+                    (11, 3),
+                    (11, 23),
+                    (11, 24),
+                ]
+            )
+        );
+        // 特例の年
+        let holidays = JapaneseCalendar::public_holidays(2019);
+        assert!(dates(2019, &[(4, 30), (5, 1), (5, 2), (10, 22)]).is_subset(&holidays));
+        assert!(!holidays.contains(&NaiveDate::from_ymd_opt(2019, 12, 23).unwrap()));
+        let holidays = JapaneseCalendar::public_holidays(2021);
+        assert!(dates(2021, &[(7, 22), (7, 23), (8, 8), (8, 9)]).is_subset(&holidays));
+        assert!(!holidays.contains(&NaiveDate::from_ymd_opt(2021, 10, 11).unwrap()));
+    }
+
+    #[test]
+    fn test_is_business_day() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2026, m, d).unwrap();
+        let calendar = JapaneseCalendar::new();
+        assert!(calendar.is_business_day(date(10, 16)));
+        assert!(!calendar.is_business_day(date(10, 17)));
+        assert!(!calendar.is_business_day(date(10, 12)));
+
+        let calendar = JapaneseCalendar::new()
+            .with_closed_weekdays([Weekday::Sun])
+            .with_holiday(date(12, 31))
+            .with_business_day(date(11, 3));
+        assert!(calendar.is_business_day(date(10, 17)));
+        assert!(!calendar.is_business_day(date(12, 31)));
+        assert!(calendar.is_business_day(date(11, 3)));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::debt::Debt;
+use crate::debt_status::{DebtStatus, DebtStatusVariable};
+use crate::debtor::{DebtorResponse, Gender, KycDone};
+use crate::remind_group::remind::RemindResponse;
+use crate::remind_group::{RemindGroup, RemindGroupStatus};
+
+/// レスポンスに含まれる `Unknown` の値を探す。`Client` の strict モードで使う
+pub(crate) trait UnknownValues {
+    /// `(項目名, 受け取った値)` の一覧。項目名はドットでつなぐ
+    fn unknown_values(&self) -> Vec<(String, String)>;
+}
+
+fn prefixed(prefix: &str, values: Vec<(String, String)>) -> Vec<(String, String)> {
+    values
+        .into_iter()
+        .map(|(field, value)| (format!("{}.{}", prefix, field), value))
+        .collect()
+}
+
+/// デコード前の値。1件ずつデコードしてから調べる
+impl UnknownValues for serde_json::Value {
+    fn unknown_values(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+}
+
+impl UnknownValues for DebtStatus {
+    fn unknown_values(&self) -> Vec<(String, String)> {
+        match &self.status {
+            DebtStatusVariable::Unknown(v) => vec![("status".into(), v.clone())],
+            _ => vec![],
+        }
+    }
+}
+
+impl UnknownValues for Debt {
+    fn unknown_values(&self) -> Vec<(String, String)> {
+        prefixed("debt_status", self.debt_status.unknown_values())
+    }
+}
+
+impl UnknownValues for DebtorResponse {
+    fn unknown_values(&self) -> Vec<(String, String)> {
+        let mut values = vec![];
+        if let Gender::Unknown(v) = &self.basic_information.gender {
+            values.push(("basic_information.gender".into(), v.clone()));
+        }
+        if let KycDone::Unknown(v) = &self.address.kyc_done {
+            values.push(("address.kyc_done".into(), v.to_string()));
+        }
+        values
+    }
+}
+
+impl UnknownValues for RemindGroup {
+    fn unknown_values(&self) -> Vec<(String, String)> {
+        match &self.status {
+            RemindGroupStatus::Unknown(v) => vec![("status".into(), v.clone())],
+            _ => vec![],
+        }
+    }
+}
+
+impl UnknownValues for RemindResponse {
+    fn unknown_values(&self) -> Vec<(String, String)> {
+        let mut values = prefixed("debtor", self.debtor.unknown_values());
+        for (i, debt) in self.debts.iter().enumerate() {
+            values.extend(prefixed(&format!("debts[{}]", i), debt.unknown_values()));
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{lecto_debt_response, lecto_debtor_response};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_unknown_values() -> anyhow::Result<()> {
+        let mut debtor = lecto_debtor_response();
+        debtor["basic_information"]["gender"] = "non_binary".into();
+        debtor["address"]["kyc_done"] = 2.into();
+        let mut debt = lecto_debt_response();
+        debt["debt_status"]["status"] = "in_negotiation".into();
+
+        let remind: RemindResponse = serde_json::from_value(serde_json::json!({
+            "label": "label",
+            "debtor": debtor,
+            "debts": [debt],
+        }))?;
+
+        assert_eq!(
+            remind.unknown_values(),
+            vec![
+                (
+                    "debtor.basic_information.gender".to_string(),
+                    "non_binary".to_string()
+                ),
+                ("debtor.address.kyc_done".into(), "2".into()),
+                (
+                    "debts[0].debt_status.status".into(),
+                    "in_negotiation".into()
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,1296 @@
+//! 結合テスト用のインプロセスなLecto API。
+//! `testing` featureで有効になる。
+//!
+//! ```no_run
+//! # async fn run() -> lecto_client::Result<()> {
+//! use lecto_client::testing::FakeLecto;
+//!
+//! let lecto = FakeLecto::start().await;
+//! let client = lecto.client()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+
+use crate::error::Result;
+use crate::idempotency::IDEMPOTENCY_KEY_HEADER;
+use crate::{Client, RetryPolicy};
+
+pub const DEFAULT_API_KEY: &str = "lecto-testing-api-key";
+
+const FAR_FUTURE: &str = "9999-12-31T23:59:59+09:00";
+
+/// リクエストに割り込ませる障害
+#[derive(Debug, Clone, PartialEq)]
+pub enum FaultKind {
+    /// 指定ステータスと `{"errors": ["injected fault"]}` を返す
+    Status(StatusCode),
+    /// 429と `Retry-After` を返す
+    TooManyRequests { retry_after_secs: u64 },
+    /// 指定時間待ってから通常どおり処理する。クライアントのタイムアウト確認用
+    Delay(Duration),
+    /// 通常どおり処理したうえで、レスポンスの代わりに指定ステータスを返す。
+    /// 登録はされたのにクライアントには失敗に見えるケースの再現用
+    LoseResponse(StatusCode),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fault {
+    pub method: Option<Method>,
+    /// パスの前方一致 (例: `/debtors`)。`None` なら全パス
+    pub path_prefix: Option<String>,
+    pub kind: FaultKind,
+    /// 発生させる回数
+    pub times: usize,
+}
+
+impl Fault {
+    pub fn new(kind: FaultKind) -> Self {
+        Self {
+            method: None,
+            path_prefix: None,
+            kind,
+            times: 1,
+        }
+    }
+
+    pub fn on(mut self, method: Method, path_prefix: impl Into<String>) -> Self {
+        self.method = Some(method);
+        self.path_prefix = Some(path_prefix.into());
+        self
+    }
+
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = times;
+        self
+    }
+
+    fn matches(&self, method: &Method, path: &str) -> bool {
+        self.times > 0
+            && self.method.as_ref().map_or(true, |m| m == method)
+            && self
+                .path_prefix
+                .as_deref()
+                .map_or(true, |p| path.starts_with(p))
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    api_key: String,
+    next_id: u64,
+    debtors: BTreeMap<String, Value>,
+    debts: BTreeMap<String, Value>,
+    debt_status_history: BTreeMap<String, Vec<Value>>,
+    remind_groups: BTreeMap<u64, RemindGroupState>,
+    faults: Vec<Fault>,
+    requests: Vec<RecordedRequest>,
+    ignore_idempotency_key: bool,
+    /// `Idempotency-Key` ごとに返したレスポンス
+    idempotent_responses: HashMap<String, (StatusCode, hyper::body::Bytes)>,
+}
+
+/// 受け付けたリクエスト
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub path: String,
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Debug)]
+struct RemindGroupState {
+    name: String,
+    status: String,
+    segments: Vec<String>,
+    schedule: Value,
+    /// 日付ごとに固定で返すリマインド。日付が `None` なら全日付共通
+    seeded: HashMap<Option<NaiveDate>, Vec<Value>>,
+}
+
+impl RemindGroupState {
+    fn new(id: u64) -> Self {
+        Self {
+            name: format!("remind group {}", id),
+            status: "active".into(),
+            segments: vec![],
+            schedule: json!({ "days_after_due": [1], "send_at": "10:00:00" }),
+            seeded: HashMap::new(),
+        }
+    }
+
+    fn to_json(&self, id: u64) -> Value {
+        json!({
+            "id": id,
+            "name": self.name,
+            "status": self.status,
+            "remind_segments": self
+                .segments
+                .iter()
+                .map(|s| json!({ "name": s }))
+                .collect::<Vec<_>>(),
+            "schedule": self.schedule,
+        })
+    }
+}
+
+impl State {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+/// インプロセスで動くLecto APIのフェイク。dropすると停止する
+pub struct FakeLecto {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl FakeLecto {
+    pub async fn start() -> Self {
+        Self::start_with_api_key(DEFAULT_API_KEY).await
+    }
+
+    pub async fn start_with_api_key(api_key: &str) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            api_key: api_key.to_string(),
+            ..Default::default()
+        }));
+
+        let make_service = {
+            let state = state.clone();
+            make_service_fn(move |_| {
+                let state = state.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        let state = state.clone();
+                        async move { Ok::<_, Infallible>(handle(state, req).await) }
+                    }))
+                }
+            })
+        };
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+        let (shutdown, rx) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            rx.await.ok();
+        }));
+
+        Self {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// このサーバーに向けた、リトライしない `Client`
+    pub fn client(&self) -> Result<Client> {
+        self.client_builder()
+            .retry_policy(RetryPolicy::none())
+            .build()
+    }
+
+    pub fn client_builder(&self) -> crate::ClientBuilder {
+        Client::builder()
+            .api_key(self.state().api_key.clone())
+            .base_url(self.url())
+    }
+
+    pub fn inject_fault(&self, fault: Fault) {
+        self.state().faults.push(fault);
+    }
+
+    pub fn clear_faults(&self) {
+        self.state().faults.clear();
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state().requests.clone()
+    }
+
+    /// `Idempotency-Key` に対応していないサーバーとして振る舞う
+    pub fn ignore_idempotency_key(&self, ignore: bool) {
+        self.state().ignore_idempotency_key = ignore;
+    }
+
+    pub fn debtor(&self, debtor_id: &str) -> Option<Value> {
+        self.state().debtors.get(debtor_id).cloned()
+    }
+
+    pub fn debt(&self, debt_id: &str) -> Option<Value> {
+        self.state().debts.get(debt_id).cloned()
+    }
+
+    /// `segments` のいずれかに属する債権をリマインド対象にする、有効なリマインドグループを登録する
+    pub fn add_remind_group(&self, remind_group_id: u64, segments: &[&str]) {
+        self.state()
+            .remind_groups
+            .entry(remind_group_id)
+            .or_insert_with(|| RemindGroupState::new(remind_group_id))
+            .segments = segments.iter().map(|s| s.to_string()).collect();
+    }
+
+    pub fn remind_group(&self, remind_group_id: u64) -> Option<Value> {
+        self.state()
+            .remind_groups
+            .get(&remind_group_id)
+            .map(|g| g.to_json(remind_group_id))
+    }
+
+    /// `GET /remind_groups/{id}/reminds` のレスポンスを固定する。`remind_at` が `None` なら全日付
+    pub fn seed_reminds(&self, remind_group_id: u64, remind_at: Option<NaiveDate>, reminds: Value) {
+        let reminds = match reminds {
+            Value::Array(v) => v,
+            v => vec![v],
+        };
+        self.state()
+            .remind_groups
+            .entry(remind_group_id)
+            .or_insert_with(|| RemindGroupState::new(remind_group_id))
+            .seeded
+            .insert(remind_at, reminds);
+    }
+
+    /// `test-data/lecto-remind-groups-reminds.json` のようなファイルから固定のリマインドを読み込み、
+    /// 含まれる債務者・債権も登録する
+    pub fn seed_reminds_from_file(
+        &self,
+        remind_group_id: u64,
+        remind_at: Option<NaiveDate>,
+        path: impl AsRef<Path>,
+    ) -> std::io::Result<()> {
+        let json = std::fs::read_to_string(path)?;
+        let reminds: Value = serde_json::from_str(&json)?;
+
+        {
+            let mut state = self.state();
+            for remind in reminds.as_array().into_iter().flatten() {
+                if let Some(debtor_id) = remind["debtor"]["debtor_id"].as_str() {
+                    state
+                        .debtors
+                        .insert(debtor_id.to_string(), remind["debtor"].clone());
+                }
+                for debt in remind["debts"].as_array().into_iter().flatten() {
+                    if let Some(debt_id) = debt["debt_id"].as_str() {
+                        state.debts.insert(debt_id.to_string(), debt.clone());
+                        state
+                            .debt_status_history
+                            .entry(debt_id.to_string())
+                            .or_default()
+                            .push(debt["debt_status"].clone());
+                    }
+                }
+            }
+        }
+
+        self.seed_reminds(remind_group_id, remind_at, reminds);
+        Ok(())
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl Drop for FakeLecto {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+async fn handle(state: Arc<Mutex<State>>, req: Request<Body>) -> Response<Body> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let query: HashMap<String, String> = req
+        .uri()
+        .query()
+        .map(|q| {
+            url::form_urlencoded::parse(q.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let idempotency_key = req
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let (fault, authorized) = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(RecordedRequest {
+            method: method.clone(),
+            path: path.clone(),
+            idempotency_key: idempotency_key.clone(),
+        });
+        let fault = state
+            .faults
+            .iter_mut()
+            .find(|f| f.matches(&method, &path))
+            .map(|f| {
+                f.times -= 1;
+                f.kind.clone()
+            });
+        let authorized = req
+            .headers()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            == Some(format!("Bearer {}", state.api_key).as_str());
+        (fault, authorized)
+    };
+
+    match fault {
+        Some(FaultKind::Status(status)) => return errors(status, &["injected fault"]),
+        Some(FaultKind::TooManyRequests { retry_after_secs }) => {
+            let mut res = errors(StatusCode::TOO_MANY_REQUESTS, &["injected fault"]);
+            res.headers_mut()
+                .insert("retry-after", retry_after_secs.to_string().parse().unwrap());
+            return res;
+        }
+        Some(FaultKind::Delay(d)) => tokio::time::sleep(d).await,
+        Some(FaultKind::LoseResponse(_)) | None => {}
+    }
+
+    if !authorized {
+        return errors(StatusCode::UNAUTHORIZED, &["Unauthorized"]);
+    }
+
+    let idempotency_key = idempotency_key
+        .filter(|_| matches!(method, Method::POST | Method::PATCH))
+        .filter(|_| !state.lock().unwrap().ignore_idempotency_key);
+    if let Some(key) = &idempotency_key {
+        let replay = state.lock().unwrap().idempotent_responses.get(key).cloned();
+        if let Some((status, bytes)) = replay {
+            let mut res = Response::new(Body::from(bytes));
+            *res.status_mut() = status;
+            res.headers_mut()
+                .insert("content-type", "application/json".parse().unwrap());
+            res.headers_mut()
+                .insert("idempotent-replayed", "true".parse().unwrap());
+            return lose_response(res, fault);
+        }
+    }
+
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) if bytes.is_empty() => Value::Null,
+        Ok(bytes) => match serde_json::from_slice(&bytes) {
+            Ok(v) => v,
+            Err(e) => return errors(StatusCode::BAD_REQUEST, &[&e.to_string()]),
+        },
+        Err(e) => return errors(StatusCode::BAD_REQUEST, &[&e.to_string()]),
+    };
+
+    let res = route(&mut state.lock().unwrap(), &method, &path, &query, body);
+
+    let res = match &idempotency_key {
+        Some(key) => {
+            let (parts, body) = res.into_parts();
+            let bytes = hyper::body::to_bytes(body).await.unwrap_or_default();
+            state
+                .lock()
+                .unwrap()
+                .idempotent_responses
+                .insert(key.clone(), (parts.status, bytes.clone()));
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        None => res,
+    };
+    lose_response(res, fault)
+}
+
+fn lose_response(res: Response<Body>, fault: Option<FaultKind>) -> Response<Body> {
+    match fault {
+        Some(FaultKind::LoseResponse(status)) => errors(status, &["injected fault"]),
+        _ => res,
+    }
+}
+
+fn route(
+    state: &mut State,
+    method: &Method,
+    path: &str,
+    query: &HashMap<String, String>,
+    body: Value,
+) -> Response<Body> {
+    let segments: Vec<String> = path.trim_matches('/').split('/').map(decode).collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    match (method, segments.as_slice()) {
+        (&Method::POST, ["debtors"]) => upsert_debtor(state, body),
+        (&Method::GET, ["debtors"]) => {
+            let items = state
+                .debtors
+                .values()
+                .filter(|d| debtor_matches(d, query))
+                .cloned()
+                .collect();
+            page(items, query)
+        }
+        (&Method::GET, ["debtors", id]) => match state.debtors.get(*id) {
+            Some(debtor) => ok(debtor.clone()),
+            None => not_found(),
+        },
+        (&Method::PATCH, ["debtors", id]) => patch_debtor(state, id, body),
+        (&Method::DELETE, ["debtors", id]) => match state.debtors.remove(*id) {
+            Some(_) => empty(StatusCode::NO_CONTENT),
+            None => not_found(),
+        },
+        (&Method::POST, ["debts"]) => upsert_debt(state, body),
+        (&Method::GET, ["debts"]) => {
+            let items = state
+                .debts
+                .values()
+                .filter(|d| debt_matches(d, query))
+                .cloned()
+                .collect();
+            page(items, query)
+        }
+        (&Method::GET, ["debts", id]) => match state.debts.get(*id) {
+            Some(debt) => ok(debt.clone()),
+            None => not_found(),
+        },
+        (&Method::PATCH, ["debts", id]) => patch_debt(state, id, body),
+        (&Method::GET, ["debt_statuses"]) => {
+            let items = query
+                .get("debt_id")
+                .and_then(|id| state.debt_status_history.get(id))
+                .cloned()
+                .unwrap_or_default();
+            page(items, query)
+        }
+        (&Method::PATCH, ["debt_statuses"]) => patch_debt_status(state, body),
+        (&Method::GET, ["remind_groups"]) => {
+            let items = state
+                .remind_groups
+                .iter()
+                .map(|(id, g)| g.to_json(*id))
+                .collect();
+            // 本物と同じく `page` / `per_page` は見ない
+            ok(Value::Array(items))
+        }
+        (&Method::POST, ["remind_groups"]) => create_remind_group(state, body),
+        (&Method::GET, ["remind_groups", id]) => {
+            match id
+                .parse()
+                .ok()
+                .and_then(|id| state.remind_groups.get(&id).map(|g| g.to_json(id)))
+            {
+                Some(group) => ok(group),
+                None => not_found(),
+            }
+        }
+        (&Method::PATCH, ["remind_groups", id]) => match id.parse::<u64>() {
+            Ok(id) => patch_remind_group(state, id, body),
+            Err(_) => not_found(),
+        },
+        (&Method::GET, ["remind_groups", id, "reminds"]) => match id.parse::<u64>() {
+            Ok(id) => reminds(state, id, query),
+            Err(_) => not_found(),
+        },
+        _ => not_found(),
+    }
+}
+
+fn upsert_debtor(state: &mut State, req: Value) -> Response<Body> {
+    let mut violations = vec![];
+    for field in ["debtor_id", "name", "address"] {
+        if req[field].as_str().map_or(true, str::is_empty) {
+            violations.push(format!("{} can't be blank", field));
+        }
+    }
+    if !req["email"].as_str().is_some_and(|e| e.contains('@')) {
+        violations.push("email is invalid".into());
+    }
+    if !matches!(req["kyc_done"].as_u64(), Some(0 | 1)) {
+        violations.push("kyc_done must be 0 or 1".into());
+    }
+    if !violations.is_empty() {
+        return errors_owned(StatusCode::UNPROCESSABLE_ENTITY, violations);
+    }
+
+    let debtor_id = req["debtor_id"].as_str().unwrap_or_default().to_string();
+    let id = match state.debtors.get(&debtor_id) {
+        Some(existing) => existing["id"].as_u64().unwrap_or_default(),
+        None => state.next_id(),
+    };
+    let debtor = json!({
+        "id": id,
+        "debtor_id": debtor_id,
+        "basic_information": {
+            "name": req["name"],
+            "name_kana": req["name_kana"],
+            "birth_date": req["birth_date"],
+            "gender": req.get("gender").cloned().unwrap_or(json!("none")),
+        },
+        "email": { "email": req["email"] },
+        "address": {
+            "address": req["address"],
+            "kyc_done": req["kyc_done"],
+            "postal_code": req["postal_code"],
+        },
+        "phone_number": {
+            "phone_number": req["phone_number"],
+            "mobile_number": req["mobile_number"],
+        },
+    });
+    state.debtors.insert(debtor_id, debtor.clone());
+    ok(debtor)
+}
+
+fn patch_debtor(state: &mut State, debtor_id: &str, req: Value) -> Response<Body> {
+    let Some(debtor) = state.debtors.get_mut(debtor_id) else {
+        return not_found();
+    };
+    if let Some(email) = req.get("email") {
+        if !email.as_str().is_some_and(|e| e.contains('@')) {
+            return errors(StatusCode::UNPROCESSABLE_ENTITY, &["email is invalid"]);
+        }
+    }
+
+    for (field, section) in [
+        ("name", "basic_information"),
+        ("name_kana", "basic_information"),
+        ("birth_date", "basic_information"),
+        ("gender", "basic_information"),
+        ("email", "email"),
+        ("address", "address"),
+        ("kyc_done", "address"),
+        ("postal_code", "address"),
+        ("phone_number", "phone_number"),
+        ("mobile_number", "phone_number"),
+    ] {
+        if let Some(v) = req.get(field) {
+            debtor[section][field] = v.clone();
+        }
+    }
+    ok(debtor.clone())
+}
+
+fn debtor_matches(debtor: &Value, query: &HashMap<String, String>) -> bool {
+    query.get("kyc_done").map_or(true, |v| {
+        debtor["address"]["kyc_done"].as_u64() == v.parse().ok()
+    }) && query.get("name").map_or(true, |v| {
+        debtor["basic_information"]["name"]
+            .as_str()
+            .is_some_and(|n| n.contains(v.as_str()))
+    }) && query
+        .get("email")
+        .map_or(true, |v| debtor["email"]["email"].as_str() == Some(v))
+}
+
+fn upsert_debt(state: &mut State, req: Value) -> Response<Body> {
+    let mut violations = vec![];
+    let debt_id = req["debt_id"].as_str().unwrap_or_default().to_string();
+    if debt_id.is_empty() {
+        violations.push("debt_id can't be blank".to_string());
+    }
+    let debtor_id = req["debtor_id"].as_str().unwrap_or_default();
+    if !state.debtors.contains_key(debtor_id) {
+        violations.push("debtor must exist".into());
+    }
+    for field in ["debt_amount", "debt_fee", "debt_delinquency_charge"] {
+        if req[field].as_i64().is_some_and(|v| v < 0) {
+            violations.push(format!("{} must be greater than or equal to 0", field));
+        }
+    }
+    if req["debt_amount"].as_i64().is_none() {
+        violations.push("debt_amount can't be blank".into());
+    }
+    match (time(&req["dealt_at"]), time(&req["repayment_due_at"])) {
+        (Some(dealt_at), Some(due)) if due < dealt_at => {
+            violations.push("repayment_due_at must be after dealt_at".into())
+        }
+        (Some(_), Some(_)) => {}
+        _ => violations.push("dealt_at and repayment_due_at must be datetime".into()),
+    }
+    if !violations.is_empty() {
+        return errors_owned(StatusCode::UNPROCESSABLE_ENTITY, violations);
+    }
+
+    let id = match state.debts.get(&debt_id) {
+        Some(existing) => existing["id"].as_u64().unwrap_or_default(),
+        None => state.next_id(),
+    };
+    let status = match req.get("debt_status").filter(|v| !v.is_null()) {
+        Some(s) => s.clone(),
+        None => json!({
+            "status": "active",
+            "changed_at": req["dealt_at"],
+            "expire_at": FAR_FUTURE,
+        }),
+    };
+    let debt_status = new_debt_status(state, &debt_id, &status);
+
+    let mut debt = json!({
+        "id": id,
+        "debt_id": debt_id,
+        "debtor_id": debtor_id,
+        "dealt_at": req["dealt_at"],
+        "debt_amount": req["debt_amount"],
+        "debt_fee": req["debt_fee"],
+        "debt_delinquency_charge": req["debt_delinquency_charge"],
+        "repayment_due_at": req["repayment_due_at"],
+        "custom_fields": req.get("custom_fields").cloned().unwrap_or(json!({})),
+        "remind_segments": [],
+        "partner": null,
+        "debt_status": debt_status,
+    });
+    apply_debt_fields(&mut debt, &req);
+    state.debts.insert(debt_id, debt.clone());
+    ok(debt)
+}
+
+fn patch_debt(state: &mut State, debt_id: &str, req: Value) -> Response<Body> {
+    let Some(debt) = state.debts.get_mut(debt_id) else {
+        return not_found();
+    };
+    for field in ["debt_amount", "debt_fee", "debt_delinquency_charge"] {
+        if req[field].as_i64().is_some_and(|v| v < 0) {
+            return errors_owned(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                vec![format!("{} must be greater than or equal to 0", field)],
+            );
+        }
+    }
+    for field in [
+        "debt_amount",
+        "debt_fee",
+        "debt_delinquency_charge",
+        "repayment_due_at",
+        "custom_fields",
+    ] {
+        if let Some(v) = req.get(field) {
+            debt[field] = v.clone();
+        }
+    }
+    apply_debt_fields(debt, &req);
+    ok(debt.clone())
+}
+
+/// `remind_segments` と `partner` をリクエストの形からレスポンスの形にする
+fn apply_debt_fields(debt: &mut Value, req: &Value) {
+    if let Some(segments) = req["remind_segments"].as_array() {
+        debt["remind_segments"] = segments.iter().map(|s| json!({ "name": s })).collect();
+    }
+    if let Some(partner) = req.get("partner").filter(|v| !v.is_null()) {
+        debt["partner"] = json!({
+            "id": partner["id"],
+            "name": partner["name"].as_str().unwrap_or_default(),
+        });
+    }
+}
+
+fn patch_debt_status(state: &mut State, req: Value) -> Response<Body> {
+    let debt_id = req["debt_id"].as_str().unwrap_or_default().to_string();
+    if !state.debts.contains_key(&debt_id) {
+        return errors(StatusCode::UNPROCESSABLE_ENTITY, &["debt must exist"]);
+    }
+    if time(&req["changed_at"]).is_none() || time(&req["expire_at"]).is_none() {
+        return errors(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            &["changed_at and expire_at must be datetime"],
+        );
+    }
+    if req.get("status").map_or(true, Value::is_null) && req.get("status_id").is_none() {
+        return errors(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            &["status or status_id is required"],
+        );
+    }
+
+    let debt_status = new_debt_status(state, &debt_id, &req);
+    if let Some(debt) = state.debts.get_mut(&debt_id) {
+        debt["debt_status"] = debt_status.clone();
+    }
+    ok(debt_status)
+}
+
+/// 新しいステータスを登録し、それまで期限なしだったステータスを `changed_at` で終わらせる
+fn new_debt_status(state: &mut State, debt_id: &str, req: &Value) -> Value {
+    let id = state.next_id();
+    if let Some(history) = state.debt_status_history.get_mut(debt_id) {
+        for prev in history.iter_mut() {
+            if prev["expire_at"].as_str() == Some(FAR_FUTURE) {
+                prev["expire_at"] = req["changed_at"].clone();
+            }
+        }
+    }
+    let debt_status = json!({
+        "id": id,
+        "debt_id": debt_id,
+        "changed_at": req["changed_at"],
+        "expire_at": req.get("expire_at").cloned().unwrap_or(json!(FAR_FUTURE)),
+        "status": req.get("status").filter(|v| !v.is_null()).cloned().unwrap_or(json!("active")),
+        "status_id": req
+            .get("status_id")
+            .filter(|v| !v.is_null())
+            .cloned()
+            .unwrap_or_else(|| json!(format!("LECTO-{}", id))),
+    });
+    state
+        .debt_status_history
+        .entry(debt_id.to_string())
+        .or_default()
+        .push(debt_status.clone());
+    debt_status
+}
+
+fn debt_matches(debt: &Value, query: &HashMap<String, String>) -> bool {
+    let due = time(&debt["repayment_due_at"]).map(|t| t.date_naive());
+    let date = |key: &str| query.get(key).and_then(|v| v.parse::<NaiveDate>().ok());
+
+    query
+        .get("debtor_id")
+        .map_or(true, |v| debt["debtor_id"].as_str() == Some(v))
+        && query
+            .get("status")
+            .map_or(true, |v| debt["debt_status"]["status"].as_str() == Some(v))
+        && query
+            .get("partner_id")
+            .map_or(true, |v| debt["partner"]["id"].as_str() == Some(v))
+        && query.get("remind_segment").map_or(true, |v| {
+            debt["remind_segments"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|s| s["name"].as_str() == Some(v))
+        })
+        && date("repayment_due_from").map_or(true, |from| due.is_some_and(|d| d >= from))
+        && date("repayment_due_to").map_or(true, |to| due.is_some_and(|d| d <= to))
+}
+
+fn remind_group_violations(req: &Value) -> Vec<String> {
+    let mut violations = vec![];
+    if req.get("name").is_some() && req["name"].as_str().map_or(true, str::is_empty) {
+        violations.push("name can't be blank".into());
+    }
+    if req.get("status").is_some() && !matches!(req["status"].as_str(), Some("active" | "paused")) {
+        violations.push("status must be active or paused".into());
+    }
+    if req.get("remind_segments").is_some() && !req["remind_segments"].is_array() {
+        violations.push("remind_segments must be an array".into());
+    }
+    if req.get("schedule").is_some()
+        && !(req["schedule"]["days_after_due"].is_array() && req["schedule"]["send_at"].is_string())
+    {
+        violations.push("schedule is invalid".into());
+    }
+    violations
+}
+
+fn update_remind_group(group: &mut RemindGroupState, req: &Value) {
+    if let Some(name) = req["name"].as_str() {
+        group.name = name.to_string();
+    }
+    if let Some(status) = req["status"].as_str() {
+        group.status = status.to_string();
+    }
+    if let Some(segments) = req["remind_segments"].as_array() {
+        group.segments = segments
+            .iter()
+            .filter_map(|s| s.as_str().map(String::from))
+            .collect();
+    }
+    if req["schedule"].is_object() {
+        group.schedule = req["schedule"].clone();
+    }
+}
+
+fn create_remind_group(state: &mut State, req: Value) -> Response<Body> {
+    let mut violations = remind_group_violations(&req);
+    for field in ["name", "remind_segments", "schedule"] {
+        if req.get(field).is_none() {
+            violations.push(format!("{} is required", field));
+        }
+    }
+    if !violations.is_empty() {
+        return errors_owned(StatusCode::UNPROCESSABLE_ENTITY, violations);
+    }
+
+    let id = state.remind_groups.keys().max().map_or(1, |id| id + 1);
+    let mut group = RemindGroupState::new(id);
+    group.status = "paused".into();
+    update_remind_group(&mut group, &req);
+    let res = group.to_json(id);
+    state.remind_groups.insert(id, group);
+    json_response(StatusCode::CREATED, res)
+}
+
+fn patch_remind_group(state: &mut State, id: u64, req: Value) -> Response<Body> {
+    let violations = remind_group_violations(&req);
+    if !violations.is_empty() {
+        return errors_owned(StatusCode::UNPROCESSABLE_ENTITY, violations);
+    }
+    match state.remind_groups.get_mut(&id) {
+        Some(group) => {
+            update_remind_group(group, &req);
+            ok(group.to_json(id))
+        }
+        None => not_found(),
+    }
+}
+
+fn reminds(state: &State, remind_group_id: u64, query: &HashMap<String, String>) -> Response<Body> {
+    let Some(group) = state.remind_groups.get(&remind_group_id) else {
+        return not_found();
+    };
+    let Some(remind_at) = query
+        .get("remind_at")
+        .and_then(|v| v.parse::<NaiveDate>().ok())
+    else {
+        return errors(StatusCode::BAD_REQUEST, &["remind_at is required"]);
+    };
+    let ignore_status = query
+        .get("ignore_remind_group_status")
+        .is_some_and(|v| v == "true");
+    if !ignore_status && group.status != "active" {
+        return ok(Value::Array(vec![]));
+    }
+
+    if let Some(seeded) = group
+        .seeded
+        .get(&Some(remind_at))
+        .or_else(|| group.seeded.get(&None))
+    {
+        return ok(Value::Array(seeded.clone()));
+    }
+
+    // 期日を過ぎた有効な債権を債務者・月ごとにまとめる
+    let mut grouped: BTreeMap<(String, String), Vec<Value>> = BTreeMap::new();
+    for debt in state.debts.values() {
+        let in_segment = debt["remind_segments"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|s| group.segments.iter().any(|g| s["name"].as_str() == Some(g)));
+        let active = matches!(
+            debt["debt_status"]["status"].as_str(),
+            Some("active" | "auto_activated")
+        );
+        let due = time(&debt["repayment_due_at"]).map(|t| t.date_naive());
+        if in_segment && active && due.is_some_and(|d| d <= remind_at) {
+            let debtor_id = debt["debtor_id"].as_str().unwrap_or_default().to_string();
+            let month = due
+                .map(|d| d.format("%Y-%m").to_string())
+                .unwrap_or_default();
+            grouped
+                .entry((debtor_id, month))
+                .or_default()
+                .push(debt.clone());
+        }
+    }
+
+    let items = grouped
+        .into_iter()
+        .filter_map(|((debtor_id, month), debts)| {
+            let debtor = state.debtors.get(&debtor_id)?;
+            Some(json!({
+                "label": format!("{}---{}", debtor_id, month),
+                "debtor": debtor,
+                "debts": debts,
+            }))
+        })
+        .collect();
+    // 本物と同じく `page` / `per_page` は見ない
+    ok(Value::Array(items))
+}
+
+fn page(items: Vec<Value>, query: &HashMap<String, String>) -> Response<Body> {
+    let per_page = query
+        .get("per_page")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(items.len().max(1));
+    let page = query
+        .get("page")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    ok(Value::Array(
+        items
+            .into_iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .collect(),
+    ))
+}
+
+/// パスの1セグメントをパーセントデコードする
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn time(value: &Value) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(value.as_str()?).ok()
+}
+
+fn ok(body: Value) -> Response<Body> {
+    json_response(StatusCode::OK, body)
+}
+
+fn not_found() -> Response<Body> {
+    errors(StatusCode::NOT_FOUND, &["NotFound"])
+}
+
+fn errors(status: StatusCode, messages: &[&str]) -> Response<Body> {
+    json_response(status, json!({ "errors": messages }))
+}
+
+fn errors_owned(status: StatusCode, messages: Vec<String>) -> Response<Body> {
+    json_response(status, json!({ "errors": messages }))
+}
+
+fn empty(status: StatusCode) -> Response<Body> {
+    let mut res = Response::new(Body::empty());
+    *res.status_mut() = status;
+    res
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    let mut res = Response::new(Body::from(body.to_string()));
+    *res.status_mut() = status;
+    res.headers_mut()
+        .insert("content-type", "application/json".parse().unwrap());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use crate::remind_group::{RemindGroupRequest, RemindGroupStatus, RemindSchedule};
+    use crate::{DebtRequest, DebtStatusVariable, LectoError};
+    use assert_matches::assert_matches;
+    use chrono::NaiveTime;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_debtor_round_trip() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        let client = lecto.client()?;
+
+        let created = client
+            .post_debtor(fixture::debtor_request_sample_data())
+            .await?;
+        let fetched = client.get_debtor("test-external-id").await?;
+        assert_eq!(created, fetched);
+
+        let listed = client
+            .list_debtors(&crate::DebtorFilter {
+                kyc_done: Some(true),
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(listed, vec![created]);
+
+        client.delete_debtor("test-external-id").await?;
+        assert_matches!(
+            client.get_debtor("test-external-id").await,
+            Err(LectoError::NotFound { .. })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation_error() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        let client = lecto.client()?;
+
+        let res = client
+            .post_debtor(crate::DebtorRequest {
+                email: "invalid".into(),
+                ..fixture::debtor_request_sample_data()
+            })
+            .await;
+        assert_matches!(res, Err(LectoError::UnprocessableEntity { body: Some(body), .. }) => {
+            assert_eq!(body.errors, vec!["email is invalid".to_string()]);
+        });
+
+        let res = client.post_debt(fixture::debt_request_sample_data()).await;
+        assert_matches!(res, Err(LectoError::UnprocessableEntity { .. }));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        let client = Client::builder()
+            .api_key("wrong")
+            .base_url(lecto.url())
+            .build()?;
+
+        assert_matches!(
+            client.get_debtor("x").await,
+            Err(LectoError::Unauthorized { .. })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_debt_and_status() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        let client = lecto.client()?;
+        client
+            .post_debtor(crate::DebtorRequest {
+                debtor_id: "5678".into(),
+                ..fixture::debtor_request_sample_data()
+            })
+            .await?;
+
+        let debt = client
+            .post_debt(DebtRequest {
+                debt_status: None,
+                ..fixture::debt_request_sample_data()
+            })
+            .await?;
+        assert_eq!(debt.debt_status.status, DebtStatusVariable::Active);
+
+        let status = client
+            .patch_debt_statuses(crate::DebtStatusRequest {
+                debt_id: debt.debt_id.clone(),
+                changed_at: debt.dealt_at + chrono::Duration::days(30),
+                ..fixture::debt_status_request_sample_data()
+            })
+            .await?;
+        assert_eq!(status.status, DebtStatusVariable::Repaid);
+        assert_eq!(
+            client.get_debt(&debt.debt_id).await?.debt_status.status,
+            DebtStatusVariable::Repaid
+        );
+
+        let timeline = client.debt_status_timeline(&debt.debt_id).await?;
+        assert_eq!(timeline.statuses().len(), 2);
+        assert!(timeline.is_consistent());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_faults() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        lecto.inject_fault(
+            Fault::new(FaultKind::Status(StatusCode::SERVICE_UNAVAILABLE))
+                .on(Method::GET, "/debtors")
+                .times(2),
+        );
+        lecto.inject_fault(Fault::new(FaultKind::TooManyRequests {
+            retry_after_secs: 0,
+        }));
+        let client = lecto
+            .client_builder()
+            .retry_policy(RetryPolicy::fixed(4, Duration::from_millis(10)))
+            .build()?;
+
+        assert_matches!(
+            client.get_debtor("x").await,
+            Err(LectoError::NotFound { .. })
+        );
+        assert_eq!(lecto.requests().len(), 4);
+
+        lecto.inject_fault(Fault::new(FaultKind::Delay(Duration::from_secs(2))));
+        let client = lecto
+            .client_builder()
+            .timeout(Duration::from_millis(200))
+            .retry_policy(RetryPolicy::none())
+            .build()?;
+        assert_matches!(client.get_debtor("x").await, Err(LectoError::Transport { source: e, .. }) => {
+            assert!(e.is_timeout());
+        });
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_retry() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        lecto.inject_fault(
+            Fault::new(FaultKind::LoseResponse(StatusCode::GATEWAY_TIMEOUT))
+                .on(Method::POST, "/debtors"),
+        );
+        let client = lecto
+            .client_builder()
+            .retry_policy(RetryPolicy::fixed(2, Duration::from_millis(1)))
+            .build()?;
+
+        let debtor = client
+            .post_debtor(fixture::debtor_request_sample_data())
+            .await?;
+
+        assert_matches!(&lecto.requests()[..], [first, second] => {
+            assert!(first.idempotency_key.is_some());
+            assert_eq!(first.idempotency_key, second.idempotency_key);
+        });
+        assert_eq!(
+            lecto.debtor("test-external-id").unwrap()["id"],
+            json!(debtor.id)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lookup_before_retry() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        lecto.ignore_idempotency_key(true);
+        lecto.inject_fault(
+            Fault::new(FaultKind::LoseResponse(StatusCode::BAD_GATEWAY))
+                .on(Method::POST, "/debtors"),
+        );
+        let client = lecto
+            .client_builder()
+            .retry_policy(RetryPolicy::fixed(3, Duration::from_millis(1)))
+            .lookup_before_retry(true)
+            .build()?;
+
+        client
+            .post_debtor(fixture::debtor_request_sample_data())
+            .await?;
+
+        let requests: Vec<(Method, String)> = lecto
+            .requests()
+            .into_iter()
+            .map(|r| (r.method, r.path))
+            .collect();
+        assert_eq!(
+            requests,
+            vec![
+                (Method::POST, "/debtors".to_string()),
+                (Method::GET, "/debtors/test-external-id".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_seeded_reminds() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        lecto.seed_reminds_from_file(1, None, "test-data/lecto-remind-groups-reminds.json")?;
+        let client = lecto.client()?;
+
+        let reminds = client
+            .get_reminds(1, NaiveDate::from_ymd_opt(2022, 3, 20).unwrap())
+            .await?;
+        assert_eq!(reminds[0].label, "test external id3---2022-03");
+        assert!(client.get_debt("test external id5").await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_computed_reminds() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        lecto.add_remind_group(2, &["y2021"]);
+        let client = lecto.client()?;
+        client
+            .post_debtor(crate::DebtorRequest {
+                debtor_id: "5678".into(),
+                ..fixture::debtor_request_sample_data()
+            })
+            .await?;
+        client
+            .post_debt(DebtRequest {
+                debt_status: None,
+                ..fixture::debt_request_sample_data()
+            })
+            .await?;
+
+        let before_due = client
+            .get_reminds(2, NaiveDate::from_ymd_opt(2022, 2, 1).unwrap())
+            .await?;
+        assert!(before_due.is_empty());
+
+        let reminds = client
+            .get_reminds(2, NaiveDate::from_ymd_opt(2022, 3, 2).unwrap())
+            .await?;
+        assert_matches!(&reminds[..], [remind] => {
+            assert_eq!(remind.label, "5678---2022-03");
+            assert_eq!(remind.debts[0].debt_id, "1234-4321");
+        });
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remind_group_status() -> anyhow::Result<()> {
+        let lecto = FakeLecto::start().await;
+        let client = lecto
+            .client_builder()
+            .retry_policy(RetryPolicy::none())
+            .ignore_remind_group_status(false)
+            .build()?;
+        client
+            .post_debtor(crate::DebtorRequest {
+                debtor_id: "5678".into(),
+                ..fixture::debtor_request_sample_data()
+            })
+            .await?;
+        client
+            .post_debt(DebtRequest {
+                debt_status: None,
+                ..fixture::debt_request_sample_data()
+            })
+            .await?;
+
+        let group = client
+            .create_remind_group(RemindGroupRequest {
+                name: "督促メール".into(),
+                remind_segments: vec!["y2021".into()],
+                schedule: RemindSchedule {
+                    days_after_due: vec![1, 7],
+                    send_at: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+                },
+                status: None,
+            })
+            .await?;
+        assert_eq!(group.status, RemindGroupStatus::Paused);
+        assert_eq!(client.list_remind_groups().await?, vec![group.clone()]);
+
+        let remind_at = NaiveDate::from_ymd_opt(2022, 3, 2).unwrap();
+        assert!(client.get_reminds(group.id, remind_at).await?.is_empty());
+
+        let group = client.activate_remind_group(group.id).await?;
+        assert!(group.is_active());
+        assert_eq!(client.get_remind_group(group.id).await?, group);
+        assert_eq!(client.get_reminds(group.id, remind_at).await?.len(), 1);
+
+        assert_matches!(
+            client.get_remind_group(group.id + 1).await,
+            Err(LectoError::NotFound { .. })
+        );
+        Ok(())
+    }
+}
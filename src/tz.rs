@@ -0,0 +1,33 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+
+/// 日本標準時 (UTC+09:00)。`Client` の業務タイムゾーンのデフォルト
+pub const JST: FixedOffset = match FixedOffset::east_opt(9 * 3600) {
+    Some(offset) => offset,
+    None => panic!("invalid offset"),
+};
+
+/// `tz` での現在時刻
+pub fn now(tz: &FixedOffset) -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(tz)
+}
+
+/// `tz` での今日の日付
+pub fn today(tz: &FixedOffset) -> NaiveDate {
+    now(tz).date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_jst_does_not_depend_on_host() {
+        let at = JST.with_ymd_and_hms(2022, 3, 1, 0, 0, 0).unwrap();
+        assert_eq!(at.to_rfc3339(), "2022-03-01T00:00:00+09:00");
+        assert_eq!(
+            at.with_timezone(&Utc).to_rfc3339(),
+            "2022-02-28T15:00:00+00:00"
+        );
+    }
+}
@@ -1,6 +1,12 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
 use reqwest::Url;
 
-pub fn join_url<T: AsRef<str>>(base_url: &str, paths: &[T]) -> anyhow::Result<Url> {
+use crate::error::Result;
+
+pub fn join_url<T: AsRef<str>>(base_url: &str, paths: &[T]) -> Result<Url> {
     let mut url = Url::parse(base_url.strip_suffix('/').unwrap_or(base_url))?;
     paths.iter().for_each(|path| {
         url.path_segments_mut().unwrap().push(path.as_ref());
@@ -8,6 +14,19 @@ pub fn join_url<T: AsRef<str>>(base_url: &str, paths: &[T]) -> anyhow::Result<Ur
     Ok(url)
 }
 
+/// `Retry-After` ヘッダを秒数かHTTP-dateとして解釈する
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&Utc) - Utc::now())
+        .to_std()
+        .ok()
+        .or(Some(Duration::ZERO))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -29,4 +48,15 @@ mod tests {
 
         Ok(())
     }
+
+    #[rstest]
+    #[case("120", Some(Duration::from_secs(120)))]
+    #[case("Wed, 21 Oct 2015 07:28:00 GMT", Some(Duration::ZERO))]
+    #[case("soon", None)]
+    fn test_parse_retry_after(#[case] value: &str, #[case] expected: Option<Duration>) {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, value.parse().unwrap());
+
+        assert_eq!(parse_retry_after(&headers), expected);
+    }
 }
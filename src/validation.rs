@@ -0,0 +1,226 @@
+use std::fmt;
+
+use chrono::NaiveDate;
+
+/// 項目ごとのエラーの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldErrorKind {
+    /// 空になっている
+    Required,
+    /// 形式が正しくない
+    InvalidFormat,
+    /// 値が取りうる範囲外
+    OutOfRange,
+    /// 他の項目と整合していない
+    Mismatch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// 項目名。ネストしている場合は `debt_status.debt_id` のようにドットでつなぐ
+    pub field: String,
+    pub kind: FieldErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// `validate()` の結果。1件以上の `FieldError` を持つ
+#[derive(Debug, Clone, PartialEq, Eq, Default, thiserror::Error)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<String> = self.errors.iter().map(|e| e.to_string()).collect();
+        write!(f, "{}", errors.join(", "))
+    }
+}
+
+impl ValidationErrors {
+    pub(crate) fn push(
+        &mut self,
+        field: impl Into<String>,
+        kind: FieldErrorKind,
+        message: impl Into<String>,
+    ) {
+        self.errors.push(FieldError {
+            field: field.into(),
+            kind,
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// 指定した項目のエラー
+    pub fn field(&self, field: &str) -> impl Iterator<Item = &FieldError> {
+        let field = field.to_string();
+        self.errors.iter().filter(move |e| e.field == field)
+    }
+
+    pub(crate) fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+pub(crate) fn is_blank(value: &str) -> bool {
+    value.trim().is_empty()
+}
+
+/// `local@example.com` の形か。ドメインは `.` 区切りで英数字とハイフンのみ
+pub(crate) fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c))
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..");
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|l| {
+            !l.is_empty()
+                && !l.starts_with('-')
+                && !l.ends_with('-')
+                && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    local_ok && domain_ok
+}
+
+/// `1234567` か `123-4567`
+pub(crate) fn is_postal_code(value: &str) -> bool {
+    let digits = match value.split_once('-') {
+        Some((head, tail)) if head.len() == 3 => format!("{}{}", head, tail),
+        Some(_) => return false,
+        None => value.to_string(),
+    };
+    digits.len() == 7 && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+fn phone_digits(value: &str) -> Option<String> {
+    let digits: String = value.chars().filter(|c| *c != '-').collect();
+    (digits.starts_with('0') && digits.chars().all(|c| c.is_ascii_digit())).then_some(digits)
+}
+
+/// 携帯電話番号 (`060` / `070` / `080` / `090` + 8桁)。ハイフンはあってもなくてもよい
+pub(crate) fn is_mobile_number(value: &str) -> bool {
+    phone_digits(value)
+        .is_some_and(|d| d.len() == 11 && matches!(&d[..3], "060" | "070" | "080" | "090"))
+}
+
+/// 固定電話 (0始まりの10桁) か、IP電話・携帯電話 (`0X0` + 8桁)
+pub(crate) fn is_phone_number(value: &str) -> bool {
+    phone_digits(value).is_some_and(|d| {
+        let b = d.as_bytes();
+        match d.len() {
+            10 => b[1] != b'0' && !(b"56789".contains(&b[1]) && b[2] == b'0'),
+            11 => b[1] != b'0' && b[2] == b'0',
+            _ => false,
+        }
+    })
+}
+
+/// 全角カタカナ (長音符・中点を含む) と空白のみか
+pub(crate) fn is_katakana(value: &str) -> bool {
+    value
+        .chars()
+        .all(|c| ('\u{30A1}'..='\u{30FF}').contains(&c) || c == ' ' || c == '\u{3000}')
+}
+
+/// 1900-01-01から `today` までの間か
+pub(crate) fn is_plausible_birth_date(date: NaiveDate, today: NaiveDate) -> bool {
+    NaiveDate::from_ymd_opt(1900, 1, 1).is_some_and(|min| min <= date) && date <= today
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("sample@example.com", true)]
+    #[case("first.last+tag@mail.example.co.jp", true)]
+    #[case("sample@example", false)]
+    #[case("sample.example.com", false)]
+    #[case("@example.com", false)]
+    #[case("sample@@example.com", false)]
+    #[case("sam ple@example.com", false)]
+    #[case(".sample@example.com", false)]
+    #[case("sample@-example.com", false)]
+    fn test_is_email(#[case] value: &str, #[case] expected: bool) {
+        assert_eq!(is_email(value), expected);
+    }
+
+    #[rstest]
+    #[case("3336666", true)]
+    #[case("333-6666", true)]
+    #[case("33-36666", false)]
+    #[case("333666", false)]
+    #[case("333-66666", false)]
+    #[case("３３３６６６６", false)]
+    fn test_is_postal_code(#[case] value: &str, #[case] expected: bool) {
+        assert_eq!(is_postal_code(value), expected);
+    }
+
+    #[rstest]
+    #[case("0312345678", true)]
+    #[case("03-1234-5678", true)]
+    #[case("0120-123-456", true)]
+    #[case("05012345678", true)]
+    #[case("09012345678", true)]
+    #[case("0901234567", false)]
+    #[case("312345678", false)]
+    #[case("03-1234-567a", false)]
+    fn test_is_phone_number(#[case] value: &str, #[case] expected: bool) {
+        assert_eq!(is_phone_number(value), expected);
+    }
+
+    #[rstest]
+    #[case("09012345678", true)]
+    #[case("080-1234-5678", true)]
+    #[case("07012345678", true)]
+    #[case("05012345678", false)]
+    #[case("0312345678", false)]
+    #[case("0901234567", false)]
+    fn test_is_mobile_number(#[case] value: &str, #[case] expected: bool) {
+        assert_eq!(is_mobile_number(value), expected);
+    }
+
+    #[rstest]
+    #[case("ヤマダ タロウ", true)]
+    #[case("ヤマダ　ジョー・ヴィー", true)]
+    #[case("やまだ", false)]
+    #[case("ﾔﾏﾀﾞ", false)]
+    #[case("山田", false)]
+    fn test_is_katakana(#[case] value: &str, #[case] expected: bool) {
+        assert_eq!(is_katakana(value), expected);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut errors = ValidationErrors::default();
+        errors.push("email", FieldErrorKind::InvalidFormat, "invalid email");
+        errors.push("name", FieldErrorKind::Required, "is required");
+
+        assert_eq!(
+            errors.to_string(),
+            "email: invalid email, name: is required"
+        );
+        assert_eq!(errors.field("name").count(), 1);
+    }
+}